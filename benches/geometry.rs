@@ -0,0 +1,156 @@
+use std::{collections::HashMap, hint::black_box};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use prost::Message;
+
+use mapstick::{
+    Tile,
+    encoder::encode_geometry,
+    geometry::{
+        Command, Coord, DecodedCommand, DecodedParameter, TypedGeometry, commands, sequences,
+    },
+    tile::GeomType,
+};
+
+// (type, encoded geometry) of every feature of tile1.mvt
+fn tile_geometries() -> Vec<(GeomType, Vec<u32>)> {
+    let tile = Tile::decode(include_bytes!("../tile1.mvt").as_slice()).unwrap();
+    tile.layers
+        .into_iter()
+        .flat_map(|layer| layer.features)
+        .map(|feature| (feature.r#type(), feature.geometry))
+        .collect()
+}
+
+// A zigzag line with a million vertices
+fn long_line() -> (GeomType, Vec<u32>) {
+    let line = (0..1_000_000)
+        .map(|i| Coord {
+            x: i % 4096,
+            y: (i % 2) * 10,
+        })
+        .collect();
+    encode_geometry(&TypedGeometry::LineString(line))
+}
+
+// The decoder as it was before Commands: a HashMap lookup and a Vec of
+// parameters per command, kept as the baseline to compare against
+struct Operation {
+    command: Command,
+    params: Vec<DecodedParameter>,
+}
+
+fn baseline_operations(encoded: &[u32]) -> Result<Vec<Operation>, String> {
+    let command_params_count: HashMap<Command, u32> = HashMap::from([
+        (Command::MoveTo, 2),
+        (Command::LineTo, 2),
+        (Command::ClosePath, 0),
+    ]);
+
+    let mut enc_iter = encoded.iter();
+
+    let mut res = vec![];
+
+    loop {
+        let Some(command_int) = enc_iter.next() else {
+            if res.is_empty() {
+                return Err("empty vector of geometry ints".to_owned());
+            } else {
+                break;
+            }
+        };
+
+        let command = DecodedCommand::try_from(*command_int)?;
+        if res.is_empty() && command.command() != Command::MoveTo {
+            return Err("first command is not MoveTo".to_owned());
+        }
+        for _ in 0..command.count() {
+            let params_count = *command_params_count.get(&command.command()).unwrap();
+            let mut params = Vec::with_capacity(params_count.try_into().unwrap());
+
+            for _ in 0..params_count {
+                let Some(next_param_int) = enc_iter.next() else {
+                    return Err("not enough params".to_owned());
+                };
+
+                params.push(DecodedParameter::from(*next_param_int));
+            }
+            res.push(Operation {
+                command: command.command(),
+                params,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+fn baseline_sequences(operations: &[Operation]) -> Vec<Vec<Coord>> {
+    let mut res: Vec<Vec<Coord>> = vec![];
+    let mut cursor = Coord { x: 0, y: 0 };
+    for operation in operations.iter() {
+        if operation.command == Command::ClosePath {
+            continue;
+        }
+        cursor = Coord {
+            x: cursor.x.wrapping_add(operation.params[0].raw_value),
+            y: cursor.y.wrapping_add(operation.params[1].raw_value),
+        };
+        match (operation.command, res.last_mut()) {
+            (Command::LineTo, Some(sequence)) => sequence.push(cursor),
+            _ => res.push(vec![cursor]),
+        }
+    }
+    res
+}
+
+fn decode(c: &mut Criterion, name: &str, geometries: &[(GeomType, Vec<u32>)]) {
+    let mut group = c.benchmark_group(name);
+    // Absolute coordinates, the part of decoding the typed geometry that the
+    // old decoder did
+    group.bench_function("baseline", |b| {
+        b.iter(|| {
+            for (_, encoded) in geometries {
+                let operations = baseline_operations(encoded).unwrap();
+                black_box(baseline_sequences(&operations));
+            }
+        })
+    });
+    group.bench_function("sequences", |b| {
+        b.iter(|| {
+            for (_, encoded) in geometries {
+                black_box(sequences(commands(encoded)).unwrap());
+            }
+        })
+    });
+    group.bench_function("commands", |b| {
+        b.iter(|| {
+            for (geom_type, encoded) in geometries {
+                black_box(TypedGeometry::decode(commands(encoded), *geom_type).ok());
+            }
+        })
+    });
+    // The iteration alone, as done by path builders that need no TypedGeometry
+    group.bench_function("commands_only", |b| {
+        b.iter(|| {
+            for (_, encoded) in geometries {
+                let vertices = commands(encoded)
+                    .filter(|step| !matches!(step, Ok((Command::ClosePath, _, _))))
+                    .count();
+                black_box(vertices);
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_tile(c: &mut Criterion) {
+    decode(c, "decode_tile1", &tile_geometries());
+}
+
+fn bench_long_line(c: &mut Criterion) {
+    decode(c, "decode_long_line", &[long_line()]);
+}
+
+criterion_group!(benches, bench_tile, bench_long_line);
+criterion_main!(benches);
@@ -0,0 +1,153 @@
+use std::{f64::consts::TAU, hint::black_box};
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use prost::Message;
+use vello::{
+    Scene,
+    kurbo::{BezPath, Rect},
+};
+
+use mapstick::{
+    Tile,
+    camera::zoom_bucket_scale,
+    clip::clip_path,
+    decoded_tile::DecodedTile,
+    encoder::{LayerEncoder, encode_tile},
+    geometry::{Coord, Polygon, TypedGeometry},
+    path::{PathType, create_path},
+    simplify::{Junctions, simplify_paths},
+    tile_loader::decode_layers,
+};
+
+const SYNTHETIC_EXTENT: u32 = 65536;
+
+// About two million vertices: a grid of 1024 round polygons and 1000 zigzag
+// lines sticking out of the tile on both sides, so that clipping has work to do
+fn synthetic_tile() -> Vec<u8> {
+    let mut polygons = LayerEncoder::new("water", SYNTHETIC_EXTENT);
+    for i in 0..1024 {
+        let center = (
+            (i % 32) as f64 * 2048.0 + 1024.0,
+            (i / 32) as f64 * 2048.0 + 1024.0,
+        );
+        let exterior = (0..1000)
+            .map(|j| {
+                let angle = j as f64 * TAU / 1000.0;
+                Coord {
+                    x: (center.0 + 900.0 * angle.cos()) as i32,
+                    y: (center.1 + 900.0 * angle.sin()) as i32,
+                }
+            })
+            .collect();
+        let polygon = TypedGeometry::Polygon(Polygon {
+            exterior,
+            interiors: vec![],
+        });
+        polygons.add_feature(Some(i), &polygon, &[]);
+    }
+
+    let mut lines = LayerEncoder::new("transportation", SYNTHETIC_EXTENT);
+    for i in 0..1000 {
+        let line = (0..1000)
+            .map(|j| Coord {
+                x: -4096 + j * 74,
+                y: i * 65 + (j % 2) * 50,
+            })
+            .collect();
+        lines.add_feature(Some(i as u64), &TypedGeometry::LineString(line), &[]);
+    }
+
+    encode_tile(vec![polygons, lines]).encode_to_vec()
+}
+
+fn bench_tile(c: &mut Criterion, name: &str, data: &[u8]) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+
+    group.bench_function("prost_decode", |b| {
+        b.iter(|| black_box(Tile::decode(data).unwrap()))
+    });
+    group.bench_function("decode_layers", |b| {
+        b.iter(|| black_box(decode_layers(data, None).unwrap()))
+    });
+
+    let layers = decode_layers(data, None).unwrap();
+    group.bench_function("create_path", |b| {
+        b.iter(|| {
+            for layer in &layers {
+                for feature in &layer.features {
+                    if let Ok((geometry, _)) = feature.geometry() {
+                        black_box(create_path(&geometry, layer.extent()));
+                    }
+                }
+            }
+        })
+    });
+
+    let paths: Vec<_> = layers
+        .iter()
+        .flat_map(|layer| {
+            layer
+                .features
+                .iter()
+                .filter_map(|feature| create_path(&feature.geometry().ok()?.0, layer.extent()))
+        })
+        .collect();
+    // Like cutting an overzoomed child out of the tile
+    let quarter = Rect::new(0.0, 0.0, 0.5, 0.5);
+    group.bench_function("clip_path", |b| {
+        b.iter(|| {
+            for (path_type, bez_path) in &paths {
+                let closed = matches!(path_type, PathType::Fill);
+                black_box(clip_path(bez_path, quarter, closed));
+            }
+        })
+    });
+
+    group.bench_function("decoded_tile", |b| {
+        b.iter_batched(
+            || decode_layers(data, None).unwrap(),
+            |layers| black_box(DecodedTile::new(layers, 1.0 / 64.0)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    let tile = DecodedTile::new(layers, 1.0 / 64.0);
+    let tile_paths: Vec<(&BezPath, bool)> = tile
+        .paths
+        .iter()
+        .map(|path| (&path.bez_path, path.is_closed()))
+        .collect();
+    // Half a pixel on a 512 pixel wide tile
+    group.bench_function("junctions", |b| {
+        b.iter(|| black_box(Junctions::new(&tile_paths)))
+    });
+    let junctions = Junctions::new(&tile_paths);
+    group.bench_function("simplify_paths", |b| {
+        b.iter(|| black_box(simplify_paths(&tile_paths, &junctions, 0.5 / 512.0)))
+    });
+
+    let scale = zoom_bucket_scale(0);
+    group.bench_function("encode_scene", |b| {
+        b.iter(|| {
+            let mut scene = Scene::new();
+            for path in &tile.paths {
+                path.draw(&mut scene, scale);
+            }
+            black_box(scene)
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_tile1(c: &mut Criterion) {
+    bench_tile(c, "tile1", include_bytes!("../tile1.mvt"));
+}
+
+fn bench_synthetic(c: &mut Criterion) {
+    bench_tile(c, "synthetic", &synthetic_tile());
+}
+
+criterion_group!(benches, bench_tile1, bench_synthetic);
+criterion_main!(benches);
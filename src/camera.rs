@@ -0,0 +1,127 @@
+use vello::kurbo::{Affine, Point, Rect, Vec2};
+
+use crate::{projection::TILE_SIZE, tile_id::TileId};
+
+// Scene fragments are re-encoded when the scale moves to another bucket
+const ZOOM_BUCKETS_PER_OCTAVE: f64 = 2.0;
+
+// Maps world coordinates to screen pixels: the world is scaled, turned by
+// the bearing and moved by the offset
+pub struct Camera {
+    offset: Vec2,
+    scale: f64,
+    // Degrees clockwise from north of the direction the top of the screen faces
+    bearing: f64,
+}
+
+impl Camera {
+    // Shows `id` at its own zoom level in the top left corner
+    pub fn showing(id: TileId) -> Self {
+        let scale = TILE_SIZE / id.size();
+        Self {
+            offset: -id.bounds().origin().to_vec2() * scale,
+            scale,
+            bearing: 0.0,
+        }
+    }
+
+    pub fn transform(&self) -> Affine {
+        Affine::translate(self.offset)
+            * Affine::rotate(-self.bearing.to_radians())
+            * Affine::scale(self.scale)
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    // Fractional zoom level of the tiles that fit the current scale
+    pub fn tile_zoom(&self) -> f64 {
+        (self.scale / TILE_SIZE).log2()
+    }
+
+    pub fn bearing(&self) -> f64 {
+        self.bearing
+    }
+
+    pub fn pan(&mut self, delta: Vec2) {
+        self.offset += delta;
+    }
+
+    // Scales by `factor` keeping `anchor` (in screen pixels) in place
+    pub fn zoom_at(&mut self, anchor: Point, factor: f64) {
+        self.offset = anchor.to_vec2() + (self.offset - anchor.to_vec2()) * factor;
+        self.scale *= factor;
+    }
+
+    // Adds `degrees` to the bearing, turning the map counterclockwise around
+    // `anchor` (in screen pixels)
+    pub fn rotate_at(&mut self, anchor: Point, degrees: f64) {
+        let rotation = Affine::rotate_about(-degrees.to_radians(), anchor);
+        self.offset = (rotation * self.offset.to_point()).to_vec2();
+        self.bearing = (self.bearing + degrees).rem_euclid(360.0);
+    }
+
+    // Turns the map back to north up around `anchor`
+    pub fn reset_bearing(&mut self, anchor: Point) {
+        self.rotate_at(anchor, -self.bearing);
+        self.bearing = 0.0;
+    }
+
+    // The world coordinates drawn at `screen`
+    pub fn world_point(&self, screen: Point) -> Point {
+        self.transform().inverse() * screen
+    }
+
+    // Part of the world that ends up inside a width x height window
+    pub fn visible_rect(&self, width: f64, height: f64) -> Rect {
+        self.transform()
+            .inverse()
+            .transform_rect_bbox(Rect::new(0.0, 0.0, width, height))
+    }
+}
+
+// `scale` is the number of screen pixels per tile width
+pub fn zoom_bucket(scale: f64) -> i32 {
+    (scale.log2() * ZOOM_BUCKETS_PER_OCTAVE).round() as i32
+}
+
+pub fn zoom_bucket_scale(zoom_bucket: i32) -> f64 {
+    (zoom_bucket as f64 / ZOOM_BUCKETS_PER_OCTAVE).exp2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Point, b: Point) -> bool {
+        (a - b).hypot() < 1e-9
+    }
+
+    #[test]
+    fn test_rotate_at() {
+        let mut camera = Camera::showing(TileId::new(2, 1, 1));
+        let anchor = Point::new(300.0, 200.0);
+        let world = camera.world_point(anchor);
+        let east = camera.transform() * (world + (0.01, 0.0));
+
+        camera.rotate_at(anchor, 90.0);
+        assert_eq!(camera.bearing(), 90.0);
+        assert!(close(camera.transform() * world, anchor));
+        // East is up when facing east
+        let up = camera.transform() * (world + (0.01, 0.0)) - anchor;
+        assert!(close(
+            up.to_point(),
+            Point::new(0.0, -(east - anchor).hypot())
+        ));
+
+        camera.rotate_at(anchor, 300.0);
+        assert!((camera.bearing() - 30.0).abs() < 1e-9);
+        camera.reset_bearing(Point::new(10.0, 10.0));
+        assert_eq!(camera.bearing(), 0.0);
+        assert!(close(
+            camera.transform() * (world + (0.01, 0.0)) - (camera.transform() * world).to_vec2(),
+            (east - anchor).to_point()
+        ));
+    }
+}
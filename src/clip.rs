@@ -0,0 +1,258 @@
+use vello::kurbo::{BezPath, PathEl, Point, Rect, Shape};
+
+// Clips every subpath of a path made of MoveTo/LineTo/ClosePath elements, as
+// built by create_path. Closed subpaths are clipped as polygon rings, open
+// ones as lines.
+pub fn clip_path(path: &BezPath, rect: Rect, closed: bool) -> BezPath {
+    if rect.contains_rect(path.bounding_box()) {
+        return path.clone();
+    }
+
+    let mut res = BezPath::new();
+    for subpath in subpaths(path) {
+        if closed {
+            let ring = clip_ring(&subpath, rect);
+            if ring.len() >= 3 {
+                push_points(&mut res, &ring);
+                res.close_path();
+            }
+        } else {
+            for line in clip_line(&subpath, rect) {
+                push_points(&mut res, &line);
+            }
+        }
+    }
+    res
+}
+
+// Sutherland–Hodgman: clips the ring against each edge of `rect` in turn
+pub fn clip_ring(ring: &[Point], rect: Rect) -> Vec<Point> {
+    let mut output = ring.to_vec();
+    for edge in [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom] {
+        let input = std::mem::take(&mut output);
+        let Some(&last) = input.last() else {
+            break;
+        };
+        let mut prev = last;
+        for &point in &input {
+            let point_inside = edge.inside(point, rect);
+            if point_inside != edge.inside(prev, rect) {
+                output.push(edge.intersect(prev, point, rect));
+            }
+            if point_inside {
+                output.push(point);
+            }
+            prev = point;
+        }
+    }
+    output
+}
+
+// Parts of the polyline inside `rect`, a line leaving and re-entering the
+// rect becomes several lines
+pub fn clip_line(line: &[Point], rect: Rect) -> Vec<Vec<Point>> {
+    let mut res = vec![];
+    let mut current: Vec<Point> = vec![];
+    for segment in line.windows(2) {
+        let Some((start, end)) = clip_segment(segment[0], segment[1], rect) else {
+            finish_line(&mut res, &mut current);
+            continue;
+        };
+        if current.last() != Some(&start) {
+            finish_line(&mut res, &mut current);
+            current.push(start);
+        }
+        current.push(end);
+        if end != segment[1] {
+            // The line leaves the rect here
+            finish_line(&mut res, &mut current);
+        }
+    }
+    finish_line(&mut res, &mut current);
+    res
+}
+
+// Liang–Barsky, unclipped ends are returned exactly as given
+pub fn clip_segment(a: Point, b: Point, rect: Rect) -> Option<(Point, Point)> {
+    let d = b - a;
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [
+        (-d.x, a.x - rect.x0),
+        (d.x, rect.x1 - a.x),
+        (-d.y, a.y - rect.y0),
+        (d.y, rect.y1 - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+
+    let start = if t0 > 0.0 { a + d * t0 } else { a };
+    let end = if t1 < 1.0 { a + d * t1 } else { b };
+    Some((start, end))
+}
+
+fn finish_line(lines: &mut Vec<Vec<Point>>, current: &mut Vec<Point>) {
+    if current.len() >= 2 {
+        lines.push(std::mem::take(current));
+    } else {
+        current.clear();
+    }
+}
+
+pub fn subpaths(path: &BezPath) -> Vec<Vec<Point>> {
+    let mut res: Vec<Vec<Point>> = vec![];
+    for element in path.elements() {
+        match element {
+            PathEl::MoveTo(p) => res.push(vec![*p]),
+            PathEl::LineTo(p) | PathEl::QuadTo(_, p) | PathEl::CurveTo(_, _, p) => {
+                if let Some(subpath) = res.last_mut() {
+                    subpath.push(*p);
+                }
+            }
+            PathEl::ClosePath => (),
+        }
+    }
+    res
+}
+
+pub fn push_points(path: &mut BezPath, points: &[Point]) {
+    let Some((first, rest)) = points.split_first() else {
+        return;
+    };
+    path.move_to(*first);
+    for p in rest {
+        path.line_to(*p);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    fn inside(self, p: Point, rect: Rect) -> bool {
+        match self {
+            Edge::Left => p.x >= rect.x0,
+            Edge::Right => p.x <= rect.x1,
+            Edge::Top => p.y >= rect.y0,
+            Edge::Bottom => p.y <= rect.y1,
+        }
+    }
+
+    fn intersect(self, a: Point, b: Point, rect: Rect) -> Point {
+        let at_x = |x: f64| Point::new(x, a.y + (b.y - a.y) * (x - a.x) / (b.x - a.x));
+        let at_y = |y: f64| Point::new(a.x + (b.x - a.x) * (y - a.y) / (b.y - a.y), y);
+        match self {
+            Edge::Left => at_x(rect.x0),
+            Edge::Right => at_x(rect.x1),
+            Edge::Top => at_y(rect.y0),
+            Edge::Bottom => at_y(rect.y1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: Rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+
+    fn points(coords: &[(f64, f64)]) -> Vec<Point> {
+        coords.iter().map(|&(x, y)| Point::new(x, y)).collect()
+    }
+
+    #[test]
+    fn test_polygon_crossing_edge() {
+        // Square sticking out of the right edge
+        let ring = points(&[(0.5, 0.25), (1.5, 0.25), (1.5, 0.75), (0.5, 0.75)]);
+        let clipped = clip_ring(&ring, TILE);
+        assert_eq!(
+            clipped,
+            points(&[(0.5, 0.25), (1.0, 0.25), (1.0, 0.75), (0.5, 0.75)])
+        );
+    }
+
+    #[test]
+    fn test_polygon_covering_tile() {
+        let ring = points(&[(-1.0, -1.0), (2.0, -1.0), (2.0, 2.0), (-1.0, 2.0)]);
+        let clipped = clip_ring(&ring, TILE);
+        assert_eq!(clipped.len(), 4);
+        for corner in points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]) {
+            assert!(clipped.contains(&corner));
+        }
+    }
+
+    #[test]
+    fn test_polygon_outside() {
+        let ring = points(&[(2.0, 2.0), (3.0, 2.0), (3.0, 3.0)]);
+        assert!(clip_ring(&ring, TILE).is_empty());
+    }
+
+    #[test]
+    fn test_line_leaving_and_reentering() {
+        // Goes right out of the tile, down, and back in
+        let line = points(&[(0.5, 0.2), (1.5, 0.2), (1.5, 0.8), (0.5, 0.8)]);
+        let clipped = clip_line(&line, TILE);
+        assert_eq!(
+            clipped,
+            vec![
+                points(&[(0.5, 0.2), (1.0, 0.2)]),
+                points(&[(1.0, 0.8), (0.5, 0.8)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_crossing_tile() {
+        let line = points(&[(-0.5, 0.5), (0.25, 0.5), (1.5, 0.5)]);
+        assert_eq!(
+            clip_line(&line, TILE),
+            vec![points(&[(0.0, 0.5), (0.25, 0.5), (1.0, 0.5)])]
+        );
+        let outside = points(&[(-0.5, 1.5), (1.5, 1.5)]);
+        assert!(clip_line(&outside, TILE).is_empty());
+    }
+
+    #[test]
+    fn test_clip_path_with_buffer() {
+        let mut path = BezPath::new();
+        path.move_to((0.5, 0.5));
+        path.line_to((1.25, 0.5));
+        path.move_to((2.0, 2.0));
+        path.line_to((3.0, 3.0));
+
+        let clipped = clip_path(&path, TILE.inflate(0.1, 0.1), false);
+        assert_eq!(
+            clipped.elements(),
+            &[
+                PathEl::MoveTo(Point::new(0.5, 0.5)),
+                PathEl::LineTo(Point::new(1.1, 0.5)),
+            ]
+        );
+
+        let mut inside = BezPath::new();
+        inside.move_to((0.25, 0.25));
+        inside.line_to((0.75, 0.25));
+        inside.line_to((0.75, 0.75));
+        inside.close_path();
+        assert_eq!(clip_path(&inside, TILE, true), inside);
+    }
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use vello::{
+    Scene,
+    kurbo::{Affine, BezPath, Point, Rect, Shape, Stroke},
+    peniko::{Color, Fill},
+};
+
+use crate::{
+    overlay::{OVERLAY_BACKGROUND, OVERLAY_TEXT, group_thousands},
+    text::{draw_label, draw_text, text_height, text_width},
+    tile_cache::CacheStats,
+    tile_id::{TILE_BOUNDS, TileId},
+};
+
+const TILE_BORDER: Color = Color::new([1.0, 0.0, 1.0, 1.0]);
+const TILE_BUFFER: Color = Color::new([1.0, 0.0, 1.0, 0.15]);
+
+// In font pixels
+const BORDER_WIDTH: f64 = 0.5;
+const BUFFER_DASH: f64 = 3.0;
+
+// What went into a frame, shown by the debug overlay
+#[derive(Debug, Default)]
+pub struct FrameStats {
+    // Of the previous frame, this one isn't done when the overlay is drawn
+    pub frame_time: Duration,
+    pub drawn_tiles: usize,
+    pub culled_tiles: usize,
+    pub fallback_tiles: usize,
+    // Of the drawn and fallback tiles
+    pub paths: usize,
+    pub culled_paths: usize,
+    pub vertices: usize,
+    pub pending_tiles: usize,
+    pub cache: Option<CacheStats>,
+}
+
+impl FrameStats {
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("frame {:.1} ms", self.frame_time.as_secs_f64() * 1000.0),
+            format!(
+                "tiles {} drawn, {} culled, {} fallback",
+                self.drawn_tiles, self.culled_tiles, self.fallback_tiles
+            ),
+            format!(
+                "paths {} drawn, {} culled",
+                group_thousands(self.paths as u64),
+                group_thousands(self.culled_paths as u64)
+            ),
+            format!("vertices {}", group_thousands(self.vertices as u64)),
+            format!("pending {}", self.pending_tiles),
+        ];
+        if let Some(cache) = self.cache {
+            lines.push(format!(
+                "cache {} tiles, {} of {} KiB, {:.0}% hits",
+                cache.tiles,
+                group_thousands((cache.bytes / 1024) as u64),
+                group_thousands((cache.budget / 1024) as u64),
+                cache.hit_rate() * 100.0
+            ));
+        }
+        lines
+    }
+}
+
+// Draws the border of tile `id`, the buffer region around it `buffer` tile
+// widths wide, and its z/x/y in the middle. `transform` maps world coordinates
+// to the screen, `pixel` is the size of a font pixel in screen pixels.
+pub fn draw_tile_debug(scene: &mut Scene, transform: Affine, id: TileId, buffer: f64, pixel: f64) {
+    // Outlines are transformed here rather than by the scene so that strokes
+    // keep their width
+    let to_screen = transform * id.transform();
+    let outline = |rect: Rect| {
+        let mut path = rect.to_path(0.1);
+        path.apply_affine(to_screen);
+        path
+    };
+    let border = outline(TILE_BOUNDS);
+    let buffered = outline(TILE_BOUNDS.inflate(buffer, buffer));
+
+    if buffer > 0.0 {
+        let mut ring = BezPath::new();
+        ring.extend(buffered.iter());
+        ring.extend(border.iter());
+        scene.fill(Fill::EvenOdd, Affine::IDENTITY, TILE_BUFFER, None, &ring);
+        let dashes = [BUFFER_DASH * pixel, BUFFER_DASH * pixel];
+        scene.stroke(
+            &Stroke::new(BORDER_WIDTH * pixel).with_dashes(0.0, dashes),
+            Affine::IDENTITY,
+            TILE_BORDER,
+            None,
+            &buffered,
+        );
+    }
+    scene.stroke(
+        &Stroke::new(BORDER_WIDTH * pixel),
+        Affine::IDENTITY,
+        TILE_BORDER,
+        None,
+        &border,
+    );
+
+    let label = id.to_string();
+    let padding = pixel;
+    let center = to_screen * TILE_BOUNDS.center();
+    let origin = center
+        - (
+            text_width(&label, pixel) / 2.0 + padding,
+            text_height(pixel) / 2.0 + padding,
+        );
+    draw_label(
+        scene,
+        &label,
+        origin,
+        pixel,
+        padding,
+        (OVERLAY_TEXT, OVERLAY_BACKGROUND),
+    );
+}
+
+// Lines of text on a box with its top left corner at `origin`
+pub fn draw_stats(scene: &mut Scene, lines: &[String], origin: Point, pixel: f64) {
+    let padding = 2.0 * pixel;
+    let line_height = text_height(pixel) + 2.0 * pixel;
+    let width = lines
+        .iter()
+        .map(|line| text_width(line, pixel))
+        .fold(0.0, f64::max);
+    let height = lines.len() as f64 * line_height - 2.0 * pixel;
+    let background =
+        Rect::from_origin_size(origin, (width + 2.0 * padding, height + 2.0 * padding));
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        OVERLAY_BACKGROUND,
+        None,
+        &background,
+    );
+    for (i, line) in lines.iter().enumerate() {
+        let line_origin = origin + (padding, padding + i as f64 * line_height);
+        draw_text(scene, line, line_origin, pixel, OVERLAY_TEXT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_stats_lines() {
+        let mut stats = FrameStats {
+            frame_time: Duration::from_micros(16_700),
+            drawn_tiles: 6,
+            culled_tiles: 1,
+            fallback_tiles: 2,
+            paths: 12_345,
+            culled_paths: 678,
+            vertices: 1_234_567,
+            pending_tiles: 3,
+            cache: None,
+        };
+        assert_eq!(
+            stats.lines(),
+            vec![
+                "frame 16.7 ms",
+                "tiles 6 drawn, 1 culled, 2 fallback",
+                "paths 12,345 drawn, 678 culled",
+                "vertices 1,234,567",
+                "pending 3",
+            ]
+        );
+
+        stats.cache = Some(CacheStats {
+            hits: 3,
+            misses: 1,
+            tiles: 9,
+            bytes: 2048 * 1024,
+            budget: 256 * 1024 * 1024,
+        });
+        assert_eq!(
+            stats.lines().last().unwrap(),
+            "cache 9 tiles, 2,048 of 262,144 KiB, 75% hits"
+        );
+    }
+}
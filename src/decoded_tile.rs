@@ -0,0 +1,316 @@
+use vello::{
+    Scene,
+    kurbo::{BezPath, PathEl, Point, Rect},
+};
+
+use crate::{
+    camera::zoom_bucket_scale,
+    clip::clip_path,
+    geometry::RingIssue,
+    layer_wrapper::LayerWrapper,
+    path::{Path, PathType::Fill, create_path},
+    simplify::{Junctions, simplify_paths},
+    spatial_index::SpatialIndex,
+    tile_id::{TILE_BOUNDS, TileId},
+};
+
+// In screen pixels: fragments leave out details smaller than this at the
+// scale they are encoded for
+const SIMPLIFY_TOLERANCE: f64 = 0.5;
+
+// Paths are grouped for culling by the cell of a CULL_GRID x CULL_GRID grid
+// over the tile their bounding box centers fall into
+const CULL_GRID: usize = 4;
+
+pub struct DecodedTile {
+    // Sorted in drawing order
+    pub paths: Vec<Path>,
+    // Over bounding boxes of `paths`, in tile coordinates
+    pub index: SpatialIndex,
+    // Points of `paths` kept by simplification at every zoom
+    junctions: Junctions,
+    // In drawing order
+    cells: Vec<Cell>,
+    // The paths of every cell encoded in tile coordinates, with the zoom
+    // bucket they were encoded for. Colors are fixed per layer, so stroke
+    // widths that depend on the zoom bucket are the only thing that can make
+    // them stale.
+    fragments: Option<(i32, Vec<Fragment>)>,
+}
+
+// Paths of one layer type in the same grid cell, culled together. Paths of a
+// layer type share their color, so drawing them cell by cell looks the same as
+// drawing them in their original order.
+struct Cell {
+    // Union of the paths' bounding boxes, in tile coordinates
+    bounds: Rect,
+    paths: Vec<usize>,
+}
+
+pub struct Fragment {
+    pub scene: Scene,
+    // In tile coordinates
+    pub bounds: Rect,
+    // Number of paths encoded, and of their points after simplification
+    pub paths: usize,
+    pub vertices: usize,
+}
+
+impl DecodedTile {
+    // Geometry is clipped to the tile bounds grown by `buffer` (in tile widths)
+    // on every side, so that neighbouring tiles don't draw over each other
+    pub fn new(layer_wrappers: Vec<LayerWrapper>, buffer: f64) -> Self {
+        let clip_rect = TILE_BOUNDS.inflate(buffer, buffer);
+        let mut paths = Vec::new();
+        for layer_wrapper in layer_wrappers {
+            let mut zero_area_rings = 0;
+            let mut wrong_windings = 0;
+            for (i, feature) in layer_wrapper.features.iter().enumerate() {
+                let (geometry, ring_issues) = match feature.geometry() {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        log::warn!(
+                            "layer {}: skipping feature {}: {}",
+                            layer_wrapper.name(),
+                            i,
+                            e
+                        );
+                        continue;
+                    }
+                };
+                for issue in ring_issues {
+                    match issue {
+                        RingIssue::ZeroArea => zero_area_rings += 1,
+                        // Version 1 didn't specify winding order
+                        RingIssue::FirstRingInterior if layer_wrapper.version() >= 2 => {
+                            wrong_windings += 1
+                        }
+                        RingIssue::FirstRingInterior => (),
+                    }
+                }
+                // Points aren't drawn
+                let Some((path_type, bez_path)) = create_path(&geometry, layer_wrapper.extent())
+                else {
+                    continue;
+                };
+                let bez_path = clip_path(&bez_path, clip_rect, matches!(path_type, Fill));
+                if bez_path.elements().is_empty() {
+                    continue;
+                }
+                paths.push(Path::new(
+                    bez_path,
+                    layer_wrapper.color(),
+                    path_type,
+                    layer_wrapper.layer_type(),
+                ));
+            }
+            if zero_area_rings > 0 || wrong_windings > 0 {
+                log::warn!(
+                    "layer {}: dropped {} zero area rings, {} polygons start with an interior ring",
+                    layer_wrapper.name(),
+                    zero_area_rings,
+                    wrong_windings
+                );
+            }
+        }
+        // Layers that come last in LayerType are drawn first
+        paths.sort_by(|a, b| b.cmp(a));
+
+        Self::from_sorted_paths(paths)
+    }
+
+    pub fn empty() -> Self {
+        Self::from_sorted_paths(vec![])
+    }
+
+    // The part of `parent` covered by its descendant `id`, scaled up to the
+    // tile coordinates of `id` and clipped like in `new`
+    pub fn overzoomed(parent: &DecodedTile, parent_id: TileId, id: TileId, buffer: f64) -> Self {
+        let transform = id.transform().inverse() * parent_id.transform();
+        let clip_rect = TILE_BOUNDS.inflate(buffer, buffer);
+        let mut candidates = parent
+            .index
+            .search(transform.inverse().transform_rect_bbox(clip_rect));
+        candidates.sort_unstable();
+        let paths = candidates
+            .into_iter()
+            .filter_map(|i| parent.paths[i].transformed_and_clipped(transform, clip_rect))
+            .collect();
+
+        Self::from_sorted_paths(paths)
+    }
+
+    fn from_sorted_paths(paths: Vec<Path>) -> Self {
+        let bboxes: Vec<Rect> = paths.iter().map(Path::bounding_box).collect();
+        let index = SpatialIndex::new(&bboxes);
+        let junctions = Junctions::new(&outlines(&paths));
+        let cells = cells(&paths);
+
+        Self {
+            paths,
+            index,
+            junctions,
+            cells,
+            fragments: None,
+        }
+    }
+
+    pub fn bounds(&self) -> Option<Rect> {
+        self.index.bounds()
+    }
+
+    // One fragment per cell, in drawing order
+    pub fn fragments(&mut self, zoom_bucket: i32) -> &[Fragment] {
+        if self
+            .fragments
+            .as_ref()
+            .is_none_or(|(bucket, _)| *bucket != zoom_bucket)
+        {
+            log::debug!("encoding tile fragments for zoom bucket {}", zoom_bucket);
+            let scale = zoom_bucket_scale(zoom_bucket);
+            let simplified = simplify_paths(
+                &outlines(&self.paths),
+                &self.junctions,
+                SIMPLIFY_TOLERANCE / scale,
+            );
+            let fragments = self
+                .cells
+                .iter()
+                .map(|cell| {
+                    let mut scene = Scene::new();
+                    let mut vertices = 0;
+                    for &i in &cell.paths {
+                        self.paths[i].draw_as(&mut scene, scale, &simplified[i]);
+                        vertices += simplified[i]
+                            .elements()
+                            .iter()
+                            .filter(|el| !matches!(el, PathEl::ClosePath))
+                            .count();
+                    }
+                    Fragment {
+                        scene,
+                        bounds: cell.bounds,
+                        paths: cell.paths.len(),
+                        vertices,
+                    }
+                })
+                .collect();
+            self.fragments = Some((zoom_bucket, fragments));
+        }
+
+        &self.fragments.as_ref().unwrap().1
+    }
+
+    // Approximate memory usage in bytes
+    pub fn approx_size(&self) -> usize {
+        let paths: usize = self
+            .paths
+            .iter()
+            .map(|path| size_of::<Path>() + size_of_val(path.bez_path.elements()))
+            .sum();
+        let index = self.index.len() * 2 * (size_of::<Rect>() + size_of::<usize>());
+        let junctions = self.junctions.len() * 2 * size_of::<Point>();
+        let cells = self.cells.len() * size_of::<Cell>() + self.paths.len() * size_of::<usize>();
+        let fragments = self.fragments.as_ref().map_or(0, |(_, fragments)| {
+            fragments
+                .iter()
+                .map(|fragment| {
+                    let encoding = fragment.scene.encoding();
+                    size_of::<Fragment>()
+                        + size_of_val(encoding.path_tags.as_slice())
+                        + encoding.path_data.len()
+                        + size_of_val(encoding.draw_tags.as_slice())
+                        + encoding.draw_data.len()
+                        + size_of_val(encoding.transforms.as_slice())
+                        + size_of_val(encoding.styles.as_slice())
+                })
+                .sum()
+        });
+
+        size_of::<Self>() + paths + index + junctions + cells + fragments
+    }
+}
+
+// Groups each run of paths of the same layer type by grid cell
+fn cells(paths: &[Path]) -> Vec<Cell> {
+    let to_cell = |v: f64| ((v * CULL_GRID as f64).floor().max(0.0) as usize).min(CULL_GRID - 1);
+    let mut res = vec![];
+    let mut start = 0;
+    for run in paths.chunk_by(|a, b| a == b) {
+        let mut grid: Vec<Option<Cell>> = (0..CULL_GRID * CULL_GRID).map(|_| None).collect();
+        for (i, path) in run.iter().enumerate() {
+            let bbox = path.bounding_box();
+            let center = bbox.center();
+            let cell =
+                grid[to_cell(center.y) * CULL_GRID + to_cell(center.x)].get_or_insert(Cell {
+                    bounds: bbox,
+                    paths: vec![],
+                });
+            cell.bounds = cell.bounds.union(bbox);
+            cell.paths.push(start + i);
+        }
+        res.extend(grid.into_iter().flatten());
+        start += run.len();
+    }
+    res
+}
+
+// As taken by simplification
+fn outlines(paths: &[Path]) -> Vec<(&BezPath, bool)> {
+    paths
+        .iter()
+        .map(|path| (&path.bez_path, path.is_closed()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::{kurbo::Shape, peniko::Color};
+
+    use super::*;
+    use crate::{camera::zoom_bucket, layer_wrapper::LayerType, path::PathType};
+
+    fn square(x: f64, y: f64, layer_type: LayerType) -> Path {
+        let rect = Rect::new(x, y, x + 0.125, y + 0.125);
+        let color = Color::new([0.0, 0.0, 0.0, 1.0]);
+        Path::new(rect.to_path(0.1), color, PathType::Fill, layer_type)
+    }
+
+    #[test]
+    fn test_cells() {
+        // Sorted in drawing order: water, then buildings
+        let paths = vec![
+            square(0.0, 0.0, LayerType::Water),
+            square(0.75, 0.75, LayerType::Water),
+            square(0.0625, 0.0625, LayerType::Water),
+            square(0.0, 0.0, LayerType::Building),
+            // Centers outside of the tile go to the nearest cell
+            square(1.25, -0.5, LayerType::Building),
+        ];
+        let cells = cells(&paths);
+        let groups: Vec<&[usize]> = cells.iter().map(|cell| cell.paths.as_slice()).collect();
+        assert_eq!(groups, vec![&[0, 2][..], &[1], &[3], &[4]]);
+        assert_eq!(cells[0].bounds, Rect::new(0.0, 0.0, 0.1875, 0.1875));
+        assert_eq!(cells[3].bounds, Rect::new(1.25, -0.5, 1.375, -0.375));
+    }
+
+    #[test]
+    fn test_fragment_vertices() {
+        // A zigzag far finer than a screen pixel at this scale, and a square
+        let mut zigzag = BezPath::new();
+        zigzag.move_to((0.5, 0.5));
+        for i in 1..=100 {
+            zigzag.line_to((0.5 + i as f64 * 1e-3, 0.5 + (i % 2) as f64 * 1e-6));
+        }
+        let color = Color::new([0.0, 0.0, 0.0, 1.0]);
+        let paths = vec![
+            square(0.0, 0.0, LayerType::Water),
+            Path::new(zigzag, color, PathType::StrokeLine, LayerType::Water),
+        ];
+        let mut tile = DecodedTile::from_sorted_paths(paths);
+        let fragments = tile.fragments(zoom_bucket(512.0));
+        assert_eq!(fragments.iter().map(|f| f.paths).sum::<usize>(), 2);
+        // Only the ends of the zigzag are left
+        assert_eq!(fragments.iter().map(|f| f.vertices).sum::<usize>(), 4 + 2);
+    }
+}
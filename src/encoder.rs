@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use crate::{
+    Tile,
+    geometry::{
+        Command, Coord, DecodedCommand, DecodedParameter, Polygon, TypedGeometry, signed_area,
+    },
+    properties::PropertyValue,
+    tile::{Feature, GeomType, Layer, Value},
+};
+
+// Version written into every layer, see section 4.1 of the specification
+const ENCODED_VERSION: u32 = 2;
+
+// Builds one layer, deduplicating keys and values across its features
+pub struct LayerEncoder {
+    name: String,
+    extent: u32,
+    features: Vec<Feature>,
+    keys: Vec<String>,
+    key_indices: HashMap<String, u32>,
+    values: Vec<Value>,
+    value_indices: HashMap<PropertyValue, u32>,
+}
+
+impl LayerEncoder {
+    pub fn new(name: &str, extent: u32) -> Self {
+        Self {
+            name: name.to_owned(),
+            extent,
+            features: vec![],
+            keys: vec![],
+            key_indices: HashMap::new(),
+            values: vec![],
+            value_indices: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    // Geometry is in tile coordinates (0..extent); empty geometries are skipped
+    pub fn add_feature(
+        &mut self,
+        id: Option<u64>,
+        geometry: &TypedGeometry,
+        properties: &[(String, PropertyValue)],
+    ) {
+        let (geom_type, commands) = encode_geometry(geometry);
+        if commands.is_empty() {
+            return;
+        }
+
+        let mut tags = Vec::with_capacity(properties.len() * 2);
+        for (key, value) in properties {
+            tags.push(self.key_index(key));
+            tags.push(self.value_index(value));
+        }
+
+        self.features.push(Feature {
+            id,
+            tags,
+            r#type: Some(geom_type as i32),
+            geometry: commands,
+        });
+    }
+
+    pub fn finish(self) -> Layer {
+        Layer {
+            version: ENCODED_VERSION,
+            name: self.name,
+            features: self.features,
+            keys: self.keys,
+            values: self.values,
+            extent: Some(self.extent),
+        }
+    }
+
+    fn key_index(&mut self, key: &str) -> u32 {
+        if let Some(&index) = self.key_indices.get(key) {
+            return index;
+        }
+        let index = self.keys.len() as u32;
+        self.keys.push(key.to_owned());
+        self.key_indices.insert(key.to_owned(), index);
+        index
+    }
+
+    fn value_index(&mut self, value: &PropertyValue) -> u32 {
+        if let Some(&index) = self.value_indices.get(value) {
+            return index;
+        }
+        let index = self.values.len() as u32;
+        self.values.push(Value::from(value));
+        self.value_indices.insert(value.clone(), index);
+        index
+    }
+}
+
+pub fn encode_tile(layers: Vec<LayerEncoder>) -> Tile {
+    Tile {
+        layers: layers
+            .into_iter()
+            .filter(|layer| !layer.is_empty())
+            .map(LayerEncoder::finish)
+            .collect(),
+    }
+}
+
+// Command stream of a geometry, the inverse of `TypedGeometry::decode`
+pub fn encode_geometry(geometry: &TypedGeometry) -> (GeomType, Vec<u32>) {
+    let mut writer = CommandWriter::default();
+    let geom_type = match geometry {
+        TypedGeometry::Point(point) => {
+            writer.points(std::slice::from_ref(point));
+            GeomType::Point
+        }
+        TypedGeometry::MultiPoint(points) => {
+            writer.points(points);
+            GeomType::Point
+        }
+        TypedGeometry::LineString(line) => {
+            writer.line(line);
+            GeomType::Linestring
+        }
+        TypedGeometry::MultiLineString(lines) => {
+            for line in lines {
+                writer.line(line);
+            }
+            GeomType::Linestring
+        }
+        TypedGeometry::Polygon(polygon) => {
+            writer.polygon(polygon);
+            GeomType::Polygon
+        }
+        TypedGeometry::MultiPolygon(multi_polygon) => {
+            for polygon in multi_polygon.polygons.iter() {
+                writer.polygon(polygon);
+            }
+            GeomType::Polygon
+        }
+    };
+    (geom_type, writer.commands)
+}
+
+// Parameters are deltas from the cursor, which carries over between parts
+#[derive(Default)]
+struct CommandWriter {
+    commands: Vec<u32>,
+    cursor: Coord,
+}
+
+impl CommandWriter {
+    fn points(&mut self, points: &[Coord]) {
+        if points.is_empty() {
+            return;
+        }
+        self.command(Command::MoveTo, points.len());
+        for &point in points {
+            self.param(point);
+        }
+    }
+
+    // Repeated points would be written as LineTo commands of length 0, which
+    // section 4.3.4.3 doesn't allow
+    fn line(&mut self, line: &[Coord]) {
+        let mut line = line.to_vec();
+        line.dedup();
+        // A line needs at least two points (section 4.3.4.3)
+        if line.len() < 2 {
+            return;
+        }
+        self.command(Command::MoveTo, 1);
+        self.param(line[0]);
+        self.command(Command::LineTo, line.len() - 1);
+        for &point in &line[1..] {
+            self.param(point);
+        }
+    }
+
+    // Exterior rings are written with positive area, interiors with negative
+    fn polygon(&mut self, polygon: &Polygon) {
+        self.ring(&polygon.exterior, true);
+        for interior in polygon.interiors.iter() {
+            self.ring(interior, false);
+        }
+    }
+
+    fn ring(&mut self, ring: &[Coord], exterior: bool) {
+        let mut ring = ring.to_vec();
+        ring.dedup();
+        // The closing point is implied by ClosePath
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+        if ring.len() < 3 {
+            return;
+        }
+
+        if (signed_area(&ring) > 0) != exterior {
+            ring.reverse();
+        }
+        self.command(Command::MoveTo, 1);
+        self.param(ring[0]);
+        self.command(Command::LineTo, ring.len() - 1);
+        for &point in &ring[1..] {
+            self.param(point);
+        }
+        self.command(Command::ClosePath, 1);
+    }
+
+    fn command(&mut self, command: Command, count: usize) {
+        let command = DecodedCommand::try_from((u8::from(command), count as u32))
+            .expect("command count within limits");
+        self.commands.push(u32::from(command));
+    }
+
+    fn param(&mut self, point: Coord) {
+        let dx = point.x.wrapping_sub(self.cursor.x);
+        let dy = point.y.wrapping_sub(self.cursor.y);
+        self.commands
+            .push(u32::from(&DecodedParameter { raw_value: dx }));
+        self.commands
+            .push(u32::from(&DecodedParameter { raw_value: dy }));
+        self.cursor = point;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::{
+        geometry::{MultiPolygon, RingIssue},
+        tile_loader::decode_layers,
+    };
+
+    fn coords(points: &[(i32, i32)]) -> Vec<Coord> {
+        points.iter().map(|&(x, y)| Coord { x, y }).collect()
+    }
+
+    #[test]
+    fn test_encode_geometry() {
+        // Examples from section 4.3.5 of the specification
+        let point = TypedGeometry::Point(Coord { x: 25, y: 17 });
+        assert_eq!(encode_geometry(&point), (GeomType::Point, vec![9, 50, 34]));
+
+        let points = TypedGeometry::MultiPoint(coords(&[(5, 7), (3, 2)]));
+        assert_eq!(
+            encode_geometry(&points),
+            (GeomType::Point, vec![17, 10, 14, 3, 9])
+        );
+
+        let line = TypedGeometry::LineString(coords(&[(2, 2), (2, 10), (10, 10)]));
+        assert_eq!(
+            encode_geometry(&line),
+            (GeomType::Linestring, vec![9, 4, 4, 18, 0, 16, 16, 0])
+        );
+
+        let polygon = TypedGeometry::Polygon(Polygon {
+            exterior: coords(&[(3, 6), (8, 12), (20, 34)]),
+            interiors: vec![],
+        });
+        assert_eq!(
+            encode_geometry(&polygon),
+            (GeomType::Polygon, vec![9, 6, 12, 18, 10, 12, 24, 44, 15])
+        );
+
+        // Repeated points are written once
+        let line =
+            TypedGeometry::LineString(coords(&[(2, 2), (2, 2), (2, 10), (10, 10), (10, 10)]));
+        assert_eq!(
+            encode_geometry(&line),
+            (GeomType::Linestring, vec![9, 4, 4, 18, 0, 16, 16, 0])
+        );
+        let polygon = TypedGeometry::Polygon(Polygon {
+            exterior: coords(&[(3, 6), (8, 12), (8, 12), (20, 34), (3, 6)]),
+            interiors: vec![],
+        });
+        assert_eq!(
+            encode_geometry(&polygon),
+            (GeomType::Polygon, vec![9, 6, 12, 18, 10, 12, 24, 44, 15])
+        );
+        let dot = TypedGeometry::LineString(coords(&[(1, 1), (1, 1)]));
+        assert!(encode_geometry(&dot).1.is_empty());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let polygon = Polygon {
+            exterior: coords(&[(0, 0), (100, 0), (100, 100), (0, 100)]),
+            interiors: vec![coords(&[(10, 10), (10, 20), (20, 20), (20, 10)])],
+        };
+        let geometries = [
+            TypedGeometry::Point(Coord { x: 25, y: 17 }),
+            TypedGeometry::MultiPoint(coords(&[(5, 7), (3, 2)])),
+            TypedGeometry::LineString(coords(&[(2, 2), (2, 10), (10, 10)])),
+            TypedGeometry::MultiLineString(vec![
+                coords(&[(2, 2), (2, 10), (10, 10)]),
+                coords(&[(1, 1), (3, 5)]),
+            ]),
+            TypedGeometry::MultiPolygon(MultiPolygon {
+                polygons: vec![
+                    polygon,
+                    Polygon {
+                        exterior: coords(&[(200, 200), (300, 200), (300, 300)]),
+                        interiors: vec![],
+                    },
+                ],
+            }),
+        ];
+
+        let mut encoder = LayerEncoder::new("water", 4096);
+        for (i, geometry) in geometries.iter().enumerate() {
+            let properties = vec![
+                ("class".to_owned(), PropertyValue::String("lake".to_owned())),
+                ("rank".to_owned(), PropertyValue::UInt(i as u64 % 2)),
+            ];
+            encoder.add_feature(Some(i as u64), geometry, &properties);
+        }
+        let tile = encode_tile(vec![encoder]);
+
+        let layer = &tile.layers[0];
+        assert_eq!(layer.keys, vec!["class", "rank"]);
+        assert_eq!(layer.values.len(), 3);
+        assert_eq!(layer.features[1].tags, vec![0, 0, 1, 2]);
+
+        let data = tile.encode_to_vec();
+        let layers = decode_layers(&data, None).unwrap();
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].version(), ENCODED_VERSION);
+        assert_eq!(layers[0].extent(), 4096);
+        let decoded: Vec<(TypedGeometry, Vec<RingIssue>)> = layers[0]
+            .features
+            .iter()
+            .map(|f| f.geometry().unwrap())
+            .collect();
+        for ((geometry, ring_issues), expected) in decoded.iter().zip(&geometries) {
+            assert_eq!(geometry, expected);
+            assert!(ring_issues.is_empty());
+        }
+        assert_eq!(decoded.len(), geometries.len());
+    }
+}
@@ -0,0 +1,169 @@
+use serde_json::{Map, Value, json};
+use vello::kurbo::Point;
+
+use crate::{
+    geometry::{Coord, Polygon, TypedGeometry},
+    layer_wrapper::LayerWrapper,
+    projection::extent_to_lon_lat,
+    properties::PropertyValue,
+    tile_id::TileId,
+};
+
+// Features of a layer of tile `id` as GeoJSON features in longitude/latitude.
+// With `tag_layer`, the layer name is kept in a "layer" foreign member so
+// that layers can be told apart in a merged collection.
+pub fn layer_features(layer: &LayerWrapper, id: TileId, tag_layer: bool) -> Vec<Value> {
+    let to_lon_lat = |c: Coord| {
+        let lon_lat = extent_to_lon_lat(Point::new(c.x as f64, c.y as f64), id, layer.extent());
+        json!([lon_lat.x, lon_lat.y])
+    };
+
+    layer
+        .features
+        .iter()
+        .filter_map(|feature| {
+            let geometry = match feature.geometry() {
+                Ok((geometry, _)) => geometry,
+                Err(e) => {
+                    log::warn!(
+                        "layer {}: skipping feature {:?}: {}",
+                        layer.name(),
+                        feature.id(),
+                        e
+                    );
+                    return None;
+                }
+            };
+            let properties = match layer.properties(feature) {
+                Ok(properties) => properties,
+                Err(e) => {
+                    log::warn!("layer {}: feature {:?}: {}", layer.name(), feature.id(), e);
+                    vec![]
+                }
+            };
+            let mut res = Map::new();
+            res.insert("type".to_owned(), json!("Feature"));
+            if let Some(id) = feature.id() {
+                res.insert("id".to_owned(), json!(id));
+            }
+            if tag_layer {
+                res.insert("layer".to_owned(), json!(layer.name()));
+            }
+            res.insert("geometry".to_owned(), geometry_json(&geometry, &to_lon_lat));
+            res.insert("properties".to_owned(), properties_json(&properties));
+            Some(Value::Object(res))
+        })
+        .collect()
+}
+
+pub fn feature_collection(features: Vec<Value>) -> Value {
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+// Exterior rings wind clockwise as seen on the map, RFC 7946 asks for
+// counter-clockwise ones (and clockwise holes), so rings are reversed
+fn geometry_json(geometry: &TypedGeometry, position: &impl Fn(Coord) -> Value) -> Value {
+    let positions = |coords: &[Coord]| -> Value { coords.iter().map(|&c| position(c)).collect() };
+    let ring = |coords: &[Coord]| -> Value {
+        coords
+            .first()
+            .into_iter()
+            .chain(coords.iter().skip(1).rev())
+            .chain(coords.first())
+            .map(|&c| position(c))
+            .collect()
+    };
+    let polygon = |polygon: &Polygon| -> Value {
+        std::iter::once(ring(&polygon.exterior))
+            .chain(polygon.interiors.iter().map(|r| ring(r)))
+            .collect()
+    };
+
+    let (kind, coordinates) = match geometry {
+        TypedGeometry::Point(point) => ("Point", position(*point)),
+        TypedGeometry::MultiPoint(points) => ("MultiPoint", positions(points)),
+        TypedGeometry::LineString(line) => ("LineString", positions(line)),
+        TypedGeometry::MultiLineString(lines) => (
+            "MultiLineString",
+            lines.iter().map(|l| positions(l)).collect(),
+        ),
+        TypedGeometry::Polygon(p) => ("Polygon", polygon(p)),
+        TypedGeometry::MultiPolygon(multi_polygon) => (
+            "MultiPolygon",
+            multi_polygon.polygons.iter().map(polygon).collect(),
+        ),
+    };
+    json!({
+        "type": kind,
+        "coordinates": coordinates,
+    })
+}
+
+fn properties_json(properties: &[(String, PropertyValue)]) -> Value {
+    properties
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                PropertyValue::String(v) => json!(v),
+                PropertyValue::Float(v) => json!(v),
+                PropertyValue::Double(v) => json!(v),
+                PropertyValue::Int(v) | PropertyValue::SInt(v) => json!(v),
+                PropertyValue::UInt(v) => json!(v),
+                PropertyValue::Bool(v) => json!(v),
+            };
+            (key.clone(), value)
+        })
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::{
+        encoder::{LayerEncoder, encode_tile},
+        tile_loader::decode_layers,
+    };
+
+    #[test]
+    fn test_layer_features() {
+        // Tile 1/1/0 covers longitudes 0..180 and latitudes 0..85
+        let mut encoder = LayerEncoder::new("overlay", 4096);
+        let square = [(0, 0), (2048, 0), (2048, 4096), (0, 4096)];
+        encoder.add_feature(
+            Some(3),
+            &TypedGeometry::Polygon(Polygon {
+                exterior: square.iter().map(|&(x, y)| Coord { x, y }).collect(),
+                interiors: vec![],
+            }),
+            &[("name".to_owned(), PropertyValue::String("east".to_owned()))],
+        );
+        let data = encode_tile(vec![encoder]).encode_to_vec();
+        let layers = decode_layers(&data, None).unwrap();
+
+        let features = layer_features(&layers[0], TileId::new(1, 1, 0), true);
+        assert_eq!(features.len(), 1);
+        let feature = &features[0];
+        assert_eq!(feature["id"], json!(3));
+        assert_eq!(feature["layer"], json!("overlay"));
+        assert_eq!(feature["properties"], json!({"name": "east"}));
+        assert_eq!(feature["geometry"]["type"], json!("Polygon"));
+
+        let ring = feature["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring[0], ring[4]);
+        let lon_lat = |v: &Value| (v[0].as_f64().unwrap(), v[1].as_f64().unwrap());
+        let (lon, lat) = lon_lat(&ring[0]);
+        assert!(lon.abs() < 1e-9 && (lat - 85.0511287798).abs() < 1e-6);
+        // Counter-clockwise: south along the western edge first
+        let (lon, lat) = lon_lat(&ring[1]);
+        assert!(lon.abs() < 1e-9 && lat.abs() < 1e-9);
+        let (lon, lat) = lon_lat(&ring[2]);
+        assert!((lon - 90.0).abs() < 1e-9 && lat.abs() < 1e-9);
+    }
+}
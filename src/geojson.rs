@@ -0,0 +1,202 @@
+use serde_json::{Map, Value};
+use vello::kurbo::Point;
+
+use crate::properties::PropertyValue;
+
+// A GeoJSON feature (RFC 7946), coordinates are (longitude, latitude)
+pub struct GeoFeature {
+    pub id: Option<u64>,
+    pub geometry: GeoGeometry,
+    pub properties: Vec<(String, PropertyValue)>,
+}
+
+// Single geometries are stored as one-element multi geometries
+pub enum GeoGeometry {
+    Points(Vec<Point>),
+    Lines(Vec<Vec<Point>>),
+    // Every polygon is a list of rings, the first one is the exterior
+    Polygons(Vec<Vec<Vec<Point>>>),
+}
+
+// Reads a FeatureCollection, a single Feature or a bare geometry. Features
+// without geometry are skipped, as are geometry collections.
+pub fn read_features(text: &str) -> Result<Vec<GeoFeature>, String> {
+    let root: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let root = root.as_object().ok_or("GeoJSON root is not an object")?;
+
+    let features = match member_str(root, "type")? {
+        "FeatureCollection" => root
+            .get("features")
+            .and_then(Value::as_array)
+            .ok_or("FeatureCollection without features")?
+            .iter()
+            .map(|f| f.as_object().ok_or("feature is not an object".to_owned()))
+            .collect::<Result<Vec<_>, _>>()?,
+        "Feature" => vec![root],
+        _ => {
+            return Ok(read_geometry(root)?
+                .map(|geometry| GeoFeature {
+                    id: None,
+                    geometry,
+                    properties: vec![],
+                })
+                .into_iter()
+                .collect());
+        }
+    };
+
+    let mut res = vec![];
+    for (i, feature) in features.into_iter().enumerate() {
+        let geometry = match feature.get("geometry") {
+            Some(Value::Object(geometry)) => {
+                read_geometry(geometry).map_err(|e| format!("feature {}: {}", i, e))?
+            }
+            _ => None,
+        };
+        let Some(geometry) = geometry else {
+            log::warn!("skipping feature {}: no supported geometry", i);
+            continue;
+        };
+        res.push(GeoFeature {
+            id: feature.get("id").and_then(Value::as_u64),
+            geometry,
+            properties: feature
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(read_properties)
+                .unwrap_or_default(),
+        });
+    }
+    Ok(res)
+}
+
+fn read_geometry(geometry: &Map<String, Value>) -> Result<Option<GeoGeometry>, String> {
+    let kind = member_str(geometry, "type")?;
+    if kind == "GeometryCollection" {
+        return Ok(None);
+    }
+    let coordinates = geometry
+        .get("coordinates")
+        .ok_or(format!("{} without coordinates", kind))?;
+
+    let res = match kind {
+        "Point" => GeoGeometry::Points(vec![position(coordinates)?]),
+        "MultiPoint" => GeoGeometry::Points(positions(coordinates)?),
+        "LineString" => GeoGeometry::Lines(vec![positions(coordinates)?]),
+        "MultiLineString" => GeoGeometry::Lines(list(coordinates, positions)?),
+        "Polygon" => GeoGeometry::Polygons(vec![list(coordinates, positions)?]),
+        "MultiPolygon" => GeoGeometry::Polygons(list(coordinates, |p| list(p, positions))?),
+        _ => return Err(format!("unknown geometry type {}", kind)),
+    };
+    Ok(Some(res))
+}
+
+// Nulls are dropped, nested arrays and objects are kept as JSON strings
+fn read_properties(properties: &Map<String, Value>) -> Vec<(String, PropertyValue)> {
+    properties
+        .iter()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                Value::Null => return None,
+                Value::Bool(v) => PropertyValue::Bool(*v),
+                Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+                    (Some(v), _) => PropertyValue::UInt(v),
+                    (None, Some(v)) => PropertyValue::SInt(v),
+                    _ => PropertyValue::Double(n.as_f64().unwrap_or_default()),
+                },
+                Value::String(v) => PropertyValue::String(v.clone()),
+                Value::Array(_) | Value::Object(_) => PropertyValue::String(value.to_string()),
+            };
+            Some((key.clone(), value))
+        })
+        .collect()
+}
+
+fn member_str<'a>(object: &'a Map<String, Value>, key: &str) -> Result<&'a str, String> {
+    object
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or(format!("missing {}", key))
+}
+
+fn list<T>(value: &Value, item: impl Fn(&Value) -> Result<T, String>) -> Result<Vec<T>, String> {
+    value
+        .as_array()
+        .ok_or("coordinates are not an array")?
+        .iter()
+        .map(item)
+        .collect()
+}
+
+fn positions(value: &Value) -> Result<Vec<Point>, String> {
+    list(value, position)
+}
+
+fn position(value: &Value) -> Result<Point, String> {
+    match value.as_array().map(Vec::as_slice) {
+        Some([lon, lat, ..]) => match (lon.as_f64(), lat.as_f64()) {
+            (Some(lon), Some(lat)) => Ok(Point::new(lon, lat)),
+            _ => Err("position is not numeric".to_owned()),
+        },
+        _ => Err("position needs two coordinates".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_features() {
+        let text = r#"{
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "id": 7,
+                    "geometry": {"type": "Point", "coordinates": [10.5, -20.25, 100]},
+                    "properties": {"name": "a", "rank": -3, "area": 1.5, "ok": true, "n": null}
+                },
+                {
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "MultiPolygon",
+                        "coordinates": [[[[0, 0], [1, 0], [1, 1], [0, 0]]], [[[2, 2], [3, 2], [3, 3], [2, 2]]]]
+                    },
+                    "properties": null
+                },
+                {"type": "Feature", "geometry": null, "properties": {}}
+            ]
+        }"#;
+        let features = read_features(text).unwrap();
+        assert_eq!(features.len(), 2);
+
+        assert_eq!(features[0].id, Some(7));
+        let GeoGeometry::Points(points) = &features[0].geometry else {
+            panic!("not points");
+        };
+        assert_eq!(points, &vec![Point::new(10.5, -20.25)]);
+        assert_eq!(
+            features[0].properties,
+            vec![
+                ("area".to_owned(), PropertyValue::Double(1.5)),
+                ("name".to_owned(), PropertyValue::String("a".to_owned())),
+                ("ok".to_owned(), PropertyValue::Bool(true)),
+                ("rank".to_owned(), PropertyValue::SInt(-3)),
+            ]
+        );
+
+        let GeoGeometry::Polygons(polygons) = &features[1].geometry else {
+            panic!("not polygons");
+        };
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[1][0][2], Point::new(3.0, 3.0));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(read_features("[]").is_err());
+        assert!(read_features(r#"{"type": "Point", "coordinates": [1]}"#).is_err());
+        assert!(read_features(r#"{"type": "Circle", "coordinates": [1, 2]}"#).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use crate::tile::GeomType;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Command {
@@ -21,73 +21,258 @@ impl TryFrom<u8> for Command {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug)]
-pub struct Operation {
-    pub command: Command,
-    pub params: Vec<DecodedParameter>,
+impl From<Command> for u8 {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::MoveTo => 1,
+            Command::LineTo => 2,
+            Command::ClosePath => 7,
+        }
+    }
 }
 
-#[allow(dead_code)]
-#[derive(Debug)]
-pub struct Geometry {
-    pub operations: Vec<Operation>,
+// Steps through an encoded geometry without allocating, yielding every
+// command with its (dx, dy) parameters, (0, 0) for ClosePath. Stops after the
+// first error.
+pub struct Commands<I> {
+    encoded: I,
+    command: Command,
+    // Repetitions of `command` left
+    remaining: u32,
+    started: bool,
+    failed: bool,
 }
 
-impl TryFrom<&Vec<u32>> for Geometry {
-    type Error = String;
+// Commands of a geometry decoded in memory, see reader::FeatureReader::geometry
+// for reading them straight from a tile
+pub fn commands(encoded: &[u32]) -> Commands<impl Iterator<Item = Result<u32, String>> + '_> {
+    Commands::new(encoded.iter().map(|&e| Ok(e)))
+}
+
+impl<I: Iterator<Item = Result<u32, String>>> Commands<I> {
+    pub fn new(encoded: I) -> Self {
+        Self {
+            encoded,
+            command: Command::MoveTo,
+            remaining: 0,
+            started: false,
+            failed: false,
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<(Command, i32, i32)>, String> {
+        while self.remaining == 0 {
+            let Some(encoded) = self.encoded.next() else {
+                if self.started {
+                    return Ok(None);
+                }
+                return Err("empty vector of geometry ints".to_owned());
+            };
+            let command = DecodedCommand::try_from(encoded?)?;
+            if !self.started && command.command != Command::MoveTo {
+                return Err("first command is not MoveTo".to_owned());
+            }
+            self.command = command.command;
+            self.remaining = command.count;
+        }
+        self.remaining -= 1;
+        self.started = true;
 
-    fn try_from(encoded: &Vec<u32>) -> Result<Self, Self::Error> {
-        let command_params_count: HashMap<Command, u32> = HashMap::from([
-            (Command::MoveTo, 2),
-            (Command::LineTo, 2),
-            (Command::ClosePath, 0),
-        ]);
+        if self.command == Command::ClosePath {
+            return Ok(Some((Command::ClosePath, 0, 0)));
+        }
+        let mut param = || match self.encoded.next() {
+            Some(encoded) => Ok(DecodedParameter::from(encoded?).raw_value),
+            None => Err("not enough params".to_owned()),
+        };
+        let dx = param()?;
+        let dy = param()?;
+        Ok(Some((self.command, dx, dy)))
+    }
+}
+
+impl<I: Iterator<Item = Result<u32, String>>> Iterator for Commands<I> {
+    type Item = Result<(Command, i32, i32), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let res = self.step().transpose();
+        if let Some(Err(_)) = res {
+            self.failed = true;
+        }
+        res
+    }
+}
+
+// Absolute coordinates of the points, every MoveTo starts a new sequence
+pub fn sequences<I>(commands: Commands<I>) -> Result<Vec<Vec<Coord>>, String>
+where
+    I: Iterator<Item = Result<u32, String>>,
+{
+    let mut res: Vec<Vec<Coord>> = vec![];
+    let mut cursor = Coord { x: 0, y: 0 };
+    for step in commands {
+        let (command, dx, dy) = step?;
+        if command == Command::ClosePath {
+            continue;
+        }
+        cursor = Coord {
+            x: cursor.x.wrapping_add(dx),
+            y: cursor.y.wrapping_add(dy),
+        };
+        match (command, res.last_mut()) {
+            (Command::LineTo, Some(sequence)) => sequence.push(cursor),
+            _ => res.push(vec![cursor]),
+        }
+    }
+    Ok(res)
+}
 
-        let mut enc_iter = encoded.into_iter();
+// Geometry of a feature in absolute tile coordinates (0..extent)
+#[derive(Debug, PartialEq)]
+pub enum TypedGeometry {
+    Point(Coord),
+    MultiPoint(Vec<Coord>),
+    LineString(Vec<Coord>),
+    MultiLineString(Vec<Vec<Coord>>),
+    Polygon(Polygon),
+    MultiPolygon(MultiPolygon),
+}
 
-        let mut res = vec![];
+impl TypedGeometry {
+    // Interprets the command stream according to section 4.3.4 of the specification
+    pub fn decode<I>(
+        commands: Commands<I>,
+        geom_type: GeomType,
+    ) -> Result<(Self, Vec<RingIssue>), String>
+    where
+        I: Iterator<Item = Result<u32, String>>,
+    {
+        if geom_type == GeomType::Unknown {
+            return Err("unknown geometry type".to_owned());
+        }
+        Self::from_sequences(sequences(commands)?, geom_type)
+    }
 
-        loop {
-            let Some(command_int) = enc_iter.next() else {
-                if res.is_empty() {
-                    return Err("empty vector of geometry ints".to_owned());
+    fn from_sequences(
+        mut sequences: Vec<Vec<Coord>>,
+        geom_type: GeomType,
+    ) -> Result<(Self, Vec<RingIssue>), String> {
+        let typed = match geom_type {
+            GeomType::Unknown => return Err("unknown geometry type".to_owned()),
+            GeomType::Point => {
+                let mut points: Vec<Coord> = sequences.into_iter().flatten().collect();
+                if points.len() == 1 {
+                    TypedGeometry::Point(points.remove(0))
                 } else {
-                    break;
+                    TypedGeometry::MultiPoint(points)
                 }
-            };
-
-            let command: DecodedCommand = DecodedCommand::try_from(*command_int)?;
-            if res.is_empty() && command.command != Command::MoveTo {
-                return Err("first command is not MoveTo".to_owned());
             }
-            for _ in 0..command.count {
-                let params_count = *command_params_count.get(&command.command).unwrap();
-                let mut params = Vec::with_capacity(params_count.try_into().unwrap());
+            GeomType::Linestring => {
+                if sequences.len() == 1 {
+                    TypedGeometry::LineString(sequences.remove(0))
+                } else {
+                    TypedGeometry::MultiLineString(sequences)
+                }
+            }
+            GeomType::Polygon => {
+                let (mut multi_polygon, issues) = MultiPolygon::from_rings(sequences);
+                let typed = if multi_polygon.polygons.len() == 1 {
+                    TypedGeometry::Polygon(multi_polygon.polygons.remove(0))
+                } else {
+                    TypedGeometry::MultiPolygon(multi_polygon)
+                };
+                return Ok((typed, issues));
+            }
+        };
+        Ok((typed, vec![]))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Coord {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Polygon {
+    // Winding with positive area (clockwise on screen)
+    pub exterior: Vec<Coord>,
+    // Winding with negative area
+    pub interiors: Vec<Vec<Coord>>,
+}
 
-                for _ in 0..params_count {
-                    let Some(next_param_int) = enc_iter.next() else {
-                        return Err("not enough params".to_owned());
-                    };
+#[derive(Debug, PartialEq)]
+pub struct MultiPolygon {
+    pub polygons: Vec<Polygon>,
+}
 
-                    let param: DecodedParameter = DecodedParameter::from(*next_param_int);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingIssue {
+    // Dropped, it can't be classified
+    ZeroArea,
+    // The first ring winds counter-clockwise: allowed in version 1 layers, an
+    // error in version 2. Rings are classified relative to it and flipped.
+    FirstRingInterior,
+}
 
-                    params.push(param);
+impl MultiPolygon {
+    // Section 4.3.4.4 of the specification: a ring with positive area starts a
+    // new polygon, rings with negative area are holes in the last one
+    pub fn from_rings(rings: Vec<Vec<Coord>>) -> (Self, Vec<RingIssue>) {
+        let mut issues = vec![];
+        let mut polygons: Vec<Polygon> = vec![];
+        let mut flipped = None;
+
+        for mut ring in rings {
+            let area = signed_area(&ring);
+            if area == 0 {
+                issues.push(RingIssue::ZeroArea);
+                continue;
+            }
+            let flipped = *flipped.get_or_insert_with(|| {
+                if area < 0 {
+                    issues.push(RingIssue::FirstRingInterior);
                 }
-                res.push(Operation {
-                    command: command.command,
-                    params,
-                });
+                area < 0
+            });
+            if flipped {
+                ring.reverse();
+            }
+
+            match polygons.last_mut() {
+                Some(polygon) if (area < 0) != flipped => polygon.interiors.push(ring),
+                _ => polygons.push(Polygon {
+                    exterior: ring,
+                    interiors: vec![],
+                }),
             }
         }
 
-        Ok(Self { operations: res })
+        (Self { polygons }, issues)
     }
 }
 
+// Twice the area by the surveyor's formula, positive for clockwise rings in
+// screen coordinates (y pointing down)
+pub fn signed_area(ring: &[Coord]) -> i64 {
+    let Some(last) = ring.last() else {
+        return 0;
+    };
+    let mut prev = last;
+    let mut res = 0;
+    for c in ring {
+        res += prev.x as i64 * c.y as i64 - c.x as i64 * prev.y as i64;
+        prev = c;
+    }
+    res
+}
+
 // Either a command or a parameter
 #[derive(Debug, Clone, Copy)]
-#[allow(dead_code)]
 pub struct DecodedCommand {
     command: Command,
     count: u32,
@@ -101,6 +286,29 @@ impl From<u32> for DecodedParameter {
     }
 }
 
+// Zig-zag encoding, the inverse of the above
+impl From<&DecodedParameter> for u32 {
+    fn from(param: &DecodedParameter) -> Self {
+        ((param.raw_value << 1) ^ (param.raw_value >> 31)) as u32
+    }
+}
+
+impl From<DecodedCommand> for u32 {
+    fn from(command: DecodedCommand) -> Self {
+        u8::from(command.command) as u32 | (command.count << 3)
+    }
+}
+
+impl DecodedCommand {
+    pub fn command(&self) -> Command {
+        self.command
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
 impl TryFrom<u32> for DecodedCommand {
     type Error = String;
 
@@ -118,16 +326,14 @@ pub struct DecodedParameter {
     pub raw_value: i32,
 }
 
-const MIN_DECODED_PARAM: i32 = i32::MAX;
-const MAX_DECODED_PARAM: i32 = i32::MIN + 1;
+// The specification limits parameters to -(2^31 - 1)..=2^31 - 1, zig-zag
+// encoding can also represent i32::MIN. The upper limit is i32::MAX.
+const MIN_DECODED_PARAM: i32 = i32::MIN + 1;
 
 impl TryFrom<i32> for DecodedParameter {
     type Error = String;
 
     fn try_from(value: i32) -> Result<Self, Self::Error> {
-        if value > MAX_DECODED_PARAM {
-            return Err("too large".to_owned());
-        };
         if value < MIN_DECODED_PARAM {
             return Err("too small".to_owned());
         };
@@ -185,6 +391,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_param_range() {
+        assert!(DecodedParameter::try_from(0).is_ok());
+        assert!(DecodedParameter::try_from(i32::MAX).is_ok());
+        assert!(DecodedParameter::try_from(-i32::MAX).is_ok());
+        assert!(DecodedParameter::try_from(i32::MIN).is_err());
+    }
+
+    #[test]
+    fn test_param_encoding() {
+        for value in [0, 1, -1, 25, -25, i32::MAX, i32::MIN] {
+            let encoded = u32::from(&DecodedParameter { raw_value: value });
+            assert_eq!(DecodedParameter::from(encoded).raw_value, value);
+        }
+        assert_eq!(u32::from(&DecodedParameter { raw_value: -2 }), 3);
+    }
+
+    #[test]
+    fn test_command_encoding() {
+        let command = DecodedCommand::try_from((1, 1)).unwrap();
+        assert_eq!(u32::from(command), 9);
+        let command = DecodedCommand::try_from((7, 1)).unwrap();
+        assert_eq!(u32::from(command), 15);
+    }
+
     #[test]
     fn test_command_decoding() {
         let enc_dec = vec![(9, Command::MoveTo, 1)];
@@ -196,33 +427,132 @@ mod tests {
     }
 
     #[test]
-    fn test_vec_decoding() {
-        let input = &vec![17, 10, 14, 3, 9]; // MoveTo x2
-        let geometry: Geometry = input.try_into().unwrap();
-        assert_eq!(geometry.operations.get(0).unwrap().command, Command::MoveTo);
-        assert_eq!(geometry.operations.get(0).unwrap().params.len(), 2);
+    fn test_repeated_commands() {
+        // MoveTo(5, 7)(-2, -5)
+        let steps: Vec<_> = commands(&[17, 10, 14, 3, 9])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            steps,
+            vec![(Command::MoveTo, 5, 7), (Command::MoveTo, -2, -5)]
+        );
+    }
+
+    fn ring(coords: &[(i32, i32)]) -> Vec<Coord> {
+        coords.iter().map(|&(x, y)| Coord { x, y }).collect()
+    }
+
+    #[test]
+    fn test_sequences() {
+        // MoveTo(2, 2) LineTo(1, 0)(0, 1) ClosePath MoveTo(5, 5)
+        let input = [9, 4, 4, 18, 2, 0, 0, 2, 15, 9, 6, 6];
+        assert_eq!(
+            sequences(commands(&input)).unwrap(),
+            vec![ring(&[(2, 2), (3, 2), (3, 3)]), ring(&[(6, 6)])]
+        );
+    }
+
+    #[test]
+    fn test_commands() {
+        // MoveTo(2, 2) LineTo(1, 0)(0, -1) ClosePath
+        let input = [9, 4, 4, 18, 2, 0, 0, 1, 15];
+        let steps: Vec<_> = commands(&input).collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                (Command::MoveTo, 2, 2),
+                (Command::LineTo, 1, 0),
+                (Command::LineTo, 0, -1),
+                (Command::ClosePath, 0, 0),
+            ]
+        );
+        assert_eq!(
+            sequences(commands(&input)).unwrap(),
+            vec![ring(&[(2, 2), (3, 2), (3, 1)])]
+        );
+
+        let error = |input: &[u32]| commands(input).find_map(Result::err);
+        assert_eq!(error(&[]).unwrap(), "empty vector of geometry ints");
+        assert_eq!(error(&[18, 2, 0]).unwrap(), "first command is not MoveTo");
+        assert_eq!(error(&[9, 4]).unwrap(), "not enough params");
+        assert_eq!(error(&[9, 4, 4, 3]).unwrap(), "wrong command ID");
+        // Nothing is yielded after an error
+        assert_eq!(commands(&[9, 4, 4, 3, 9, 0, 0]).count(), 2);
+    }
+
+    #[test]
+    fn test_ring_classification() {
+        let exterior = ring(&[(0, 0), (10, 0), (10, 10), (0, 10)]);
+        let hole = ring(&[(2, 2), (2, 4), (4, 4), (4, 2)]);
+        let second = ring(&[(20, 0), (30, 0), (30, 10)]);
+        assert!(signed_area(&exterior) > 0);
+        assert!(signed_area(&hole) < 0);
+
+        let (multi_polygon, issues) =
+            MultiPolygon::from_rings(vec![exterior.clone(), hole.clone(), second.clone()]);
+        assert!(issues.is_empty());
+        assert_eq!(
+            multi_polygon.polygons,
+            vec![
+                Polygon {
+                    exterior: exterior.clone(),
+                    interiors: vec![hole.clone()],
+                },
+                Polygon {
+                    exterior: second,
+                    interiors: vec![],
+                },
+            ]
+        );
+
+        let flat = ring(&[(0, 0), (5, 5), (10, 10)]);
+        let (_, issues) = MultiPolygon::from_rings(vec![exterior.clone(), flat]);
+        assert_eq!(issues, vec![RingIssue::ZeroArea]);
+    }
+
+    #[test]
+    fn test_reversed_winding() {
+        let mut exterior = ring(&[(0, 0), (10, 0), (10, 10), (0, 10)]);
+        let mut hole = ring(&[(2, 2), (2, 4), (4, 4), (4, 2)]);
+        exterior.reverse();
+        hole.reverse();
+
+        let (multi_polygon, issues) = MultiPolygon::from_rings(vec![exterior, hole]);
+        assert_eq!(issues, vec![RingIssue::FirstRingInterior]);
+        assert_eq!(multi_polygon.polygons.len(), 1);
+        let polygon = &multi_polygon.polygons[0];
+        assert!(signed_area(&polygon.exterior) > 0);
+        assert!(signed_area(&polygon.interiors[0]) < 0);
+    }
+
+    #[test]
+    fn test_typed_geometry() {
+        // MoveTo(2, 2)(1, 1)
+        let points = commands(&[17, 4, 4, 2, 2]);
+        let (typed, _) = TypedGeometry::decode(points, GeomType::Point).unwrap();
+        assert_eq!(typed, TypedGeometry::MultiPoint(ring(&[(2, 2), (3, 3)])));
+
+        // MoveTo(2, 2) LineTo(1, 0)(0, 1)
+        let line = commands(&[9, 4, 4, 18, 2, 0, 0, 2]);
+        let (typed, _) = TypedGeometry::decode(line, GeomType::Linestring).unwrap();
         assert_eq!(
-            geometry
-                .operations
-                .get(0)
-                .unwrap()
-                .params
-                .iter()
-                .map(|p| p.raw_value)
-                .collect::<Vec<_>>(),
-            vec![5, 7]
+            typed,
+            TypedGeometry::LineString(ring(&[(2, 2), (3, 2), (3, 3)]))
         );
+
+        // Same with ClosePath
+        let polygon = [9, 4, 4, 18, 2, 0, 0, 2, 15];
+        let (typed, issues) = TypedGeometry::decode(commands(&polygon), GeomType::Polygon).unwrap();
+        assert!(issues.is_empty());
         assert_eq!(
-            geometry
-                .operations
-                .get(1)
-                .unwrap()
-                .params
-                .iter()
-                .map(|p| p.raw_value)
-                .collect::<Vec<_>>(),
-            vec![-2, -5]
+            typed,
+            TypedGeometry::Polygon(Polygon {
+                exterior: ring(&[(2, 2), (3, 2), (3, 3)]),
+                interiors: vec![],
+            })
         );
+
+        assert!(TypedGeometry::decode(commands(&polygon), GeomType::Unknown).is_err());
     }
 
     // #[test]
@@ -0,0 +1,354 @@
+use vello::{
+    Scene,
+    kurbo::{Affine, BezPath, Point, Rect, Stroke},
+    peniko::Color,
+};
+
+use crate::{
+    camera::Camera,
+    clip::clip_segment,
+    overlay::OVERLAY_TEXT,
+    projection::{MAX_LATITUDE, lon_lat_to_world, world_to_lon_lat},
+    text::{draw_label, text_height, text_width},
+    tile_id::TileId,
+};
+
+// Graticule spacings in arc seconds, coarsest first
+const INTERVALS: [u32; 22] = [
+    90 * 3600,
+    45 * 3600,
+    30 * 3600,
+    20 * 3600,
+    10 * 3600,
+    5 * 3600,
+    2 * 3600,
+    3600,
+    30 * 60,
+    20 * 60,
+    15 * 60,
+    10 * 60,
+    5 * 60,
+    2 * 60,
+    60,
+    30,
+    20,
+    15,
+    10,
+    5,
+    2,
+    1,
+];
+
+// In font pixels
+const MIN_SPACING: f64 = 60.0;
+const LINE_WIDTH: f64 = 0.5;
+
+// Tile grids are drawn for zoom levels up to this, tile coordinates stay well
+// within f64 precision
+pub const MAX_TILE_GRID_ZOOM: u8 = 30;
+
+// More tile grid lines than this along either axis aren't drawn
+const MAX_TILE_GRID_LINES: u64 = 256;
+
+const GRATICULE: Color = Color::new([0.1, 0.1, 0.35, 0.8]);
+const TILE_GRID: Color = Color::new([1.0, 0.55, 0.0, 0.9]);
+
+// A line in world coordinates, labelled at its `from` end if at all
+#[derive(Debug, PartialEq)]
+pub struct GridLine {
+    pub from: Point,
+    pub to: Point,
+    pub label: Option<String>,
+}
+
+// The finest spacing in arc seconds that keeps meridians at least
+// `min_spacing` screen pixels apart, at `scale` screen pixels per world width
+pub fn graticule_interval(scale: f64, min_spacing: f64) -> u32 {
+    let pixels_per_second = scale / (360.0 * 3600.0);
+    INTERVALS
+        .iter()
+        .rev()
+        .copied()
+        .find(|&interval| interval as f64 * pixels_per_second >= min_spacing)
+        .unwrap_or(INTERVALS[0])
+}
+
+// `seconds` of arc with as much precision as `interval` calls for, the
+// hemisphere is the first or second character for positive and negative
+// angles, as in 48°51'N
+pub fn format_angle(seconds: i64, interval: u32, hemispheres: (char, char)) -> String {
+    let hemisphere = match seconds {
+        0 => String::new(),
+        s if s > 0 => hemispheres.0.to_string(),
+        _ => hemispheres.1.to_string(),
+    };
+    let seconds = seconds.unsigned_abs();
+    let (degrees, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if interval % 3600 == 0 {
+        format!("{}°{}", degrees, hemisphere)
+    } else if interval % 60 == 0 {
+        format!("{}°{:02}'{}", degrees, minutes, hemisphere)
+    } else {
+        format!("{}°{:02}'{:02}\"{}", degrees, minutes, seconds, hemisphere)
+    }
+}
+
+// Meridians from north to south and parallels from west to east crossing
+// `visible` (in world coordinates), every `interval` arc seconds
+pub fn graticule_lines(visible: Rect, interval: u32) -> Vec<GridLine> {
+    let north_west = world_to_lon_lat(visible.origin());
+    let south_east = world_to_lon_lat(Point::new(visible.x1, visible.y1));
+    let (west, east) = (north_west.x.max(-180.0), south_east.x.min(180.0));
+    let (south, north) = (
+        south_east.y.max(-MAX_LATITUDE),
+        north_west.y.min(MAX_LATITUDE),
+    );
+    if west > east || south > north {
+        return vec![];
+    }
+
+    // Multiples of the interval within min..=max degrees
+    let steps = |min: f64, max: f64| {
+        let step = interval as f64 / 3600.0;
+        ((min / step).ceil() as i64..=(max / step).floor() as i64).map(|i| i * interval as i64)
+    };
+    let mut res = vec![];
+    for seconds in steps(west, east) {
+        let lon = seconds as f64 / 3600.0;
+        res.push(GridLine {
+            from: lon_lat_to_world(Point::new(lon, north)),
+            to: lon_lat_to_world(Point::new(lon, south)),
+            label: Some(format_angle(seconds, interval, ('E', 'W'))),
+        });
+    }
+    for seconds in steps(south, north) {
+        let lat = seconds as f64 / 3600.0;
+        res.push(GridLine {
+            from: lon_lat_to_world(Point::new(west, lat)),
+            to: lon_lat_to_world(Point::new(east, lat)),
+            label: Some(format_angle(seconds, interval, ('N', 'S'))),
+        });
+    }
+    res
+}
+
+// Edges of the tiles of zoom level `z` crossing `visible`, none if there
+// would be too many
+pub fn tile_grid_lines(visible: Rect, z: u8) -> Vec<GridLine> {
+    let count = 1_u64 << z;
+    let area = Rect::new(
+        visible.x0.max(0.0),
+        visible.y0.max(0.0),
+        visible.x1.min(1.0),
+        visible.y1.min(1.0),
+    );
+    if area.x0 > area.x1 || area.y0 > area.y1 {
+        return vec![];
+    }
+    let edges = |min: f64, max: f64| {
+        let first = (min * count as f64).ceil() as u64;
+        let last = (max * count as f64).floor() as u64;
+        first..=last
+    };
+    let (columns, rows) = (edges(area.x0, area.x1), edges(area.y0, area.y1));
+    if columns.clone().count() as u64 > MAX_TILE_GRID_LINES
+        || rows.clone().count() as u64 > MAX_TILE_GRID_LINES
+    {
+        return vec![];
+    }
+
+    let line = |from: Point, to: Point| GridLine {
+        from,
+        to,
+        label: None,
+    };
+    let mut res = vec![];
+    for column in columns {
+        let x = column as f64 / count as f64;
+        res.push(line(Point::new(x, area.y0), Point::new(x, area.y1)));
+    }
+    for row in rows {
+        let y = row as f64 / count as f64;
+        res.push(line(Point::new(area.x0, y), Point::new(area.x1, y)));
+    }
+    res
+}
+
+// Draws a graticule over a width x height window showing `camera`'s view,
+// `pixel` is the size of a font pixel in screen pixels
+pub fn draw_graticule(scene: &mut Scene, camera: &Camera, width: f64, height: f64, pixel: f64) {
+    let interval = graticule_interval(camera.scale(), MIN_SPACING * pixel);
+    let lines = graticule_lines(camera.visible_rect(width, height), interval);
+    draw_lines(scene, &lines, camera, width, height, pixel, GRATICULE);
+}
+
+// Draws the grid of zoom level `z` tiles with their z/x/y where they fit
+pub fn draw_tile_grid(
+    scene: &mut Scene,
+    camera: &Camera,
+    width: f64,
+    height: f64,
+    z: u8,
+    pixel: f64,
+) {
+    let visible = camera.visible_rect(width, height);
+    let lines = tile_grid_lines(visible, z);
+    if lines.is_empty() {
+        return;
+    }
+    draw_lines(scene, &lines, camera, width, height, pixel, TILE_GRID);
+
+    let tile_width = camera.scale() * TileId::new(z, 0, 0).size();
+    if tile_width < MIN_SPACING * pixel {
+        return;
+    }
+    let padding = pixel;
+    for id in TileId::covering(visible, z) {
+        let label = id.to_string();
+        if text_width(&label, pixel) + 4.0 * padding > tile_width {
+            continue;
+        }
+        let center = camera.transform() * id.bounds().center();
+        let origin = center
+            - (
+                text_width(&label, pixel) / 2.0 + padding,
+                text_height(pixel) / 2.0 + padding,
+            );
+        draw_label(
+            scene,
+            &label,
+            origin,
+            pixel,
+            padding,
+            (OVERLAY_TEXT, TILE_GRID),
+        );
+    }
+}
+
+// Strokes `lines` at a constant width and labels them where their `from` end
+// enters the window, kept inside the window
+fn draw_lines(
+    scene: &mut Scene,
+    lines: &[GridLine],
+    camera: &Camera,
+    width: f64,
+    height: f64,
+    pixel: f64,
+    color: Color,
+) {
+    let transform = camera.transform();
+    let window = Rect::new(0.0, 0.0, width, height);
+    let mut path = BezPath::new();
+    let mut labels = vec![];
+    for line in lines {
+        let Some((from, to)) = clip_segment(transform * line.from, transform * line.to, window)
+        else {
+            continue;
+        };
+        path.move_to(from);
+        path.line_to(to);
+        if let Some(label) = &line.label {
+            labels.push((from, label));
+        }
+    }
+    scene.stroke(
+        &Stroke::new(LINE_WIDTH * pixel),
+        Affine::IDENTITY,
+        color,
+        None,
+        &path,
+    );
+
+    let padding = pixel;
+    for (anchor, label) in labels {
+        let size = (
+            text_width(label, pixel) + 2.0 * padding,
+            text_height(pixel) + 2.0 * padding,
+        );
+        let origin = Point::new(
+            anchor.x.clamp(0.0, (width - size.0).max(0.0)),
+            anchor.y.clamp(0.0, (height - size.1).max(0.0)),
+        );
+        draw_label(scene, label, origin, pixel, padding, (OVERLAY_TEXT, color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projection::TILE_SIZE;
+
+    #[test]
+    fn test_graticule_interval() {
+        // The whole world in 512 pixels
+        assert_eq!(graticule_interval(TILE_SIZE, 100.0), 90 * 3600);
+        assert_eq!(graticule_interval(TILE_SIZE, 1000.0), 90 * 3600);
+        // 1° is about 364 pixels at zoom 8
+        let scale = TILE_SIZE * 256.0;
+        assert_eq!(graticule_interval(scale, 100.0), 20 * 60);
+        assert_eq!(graticule_interval(scale, 300.0), 3600);
+        assert_eq!(graticule_interval(scale * 1e6, 100.0), 1);
+    }
+
+    #[test]
+    fn test_format_angle() {
+        assert_eq!(format_angle(0, 3600, ('N', 'S')), "0°");
+        assert_eq!(format_angle(45 * 3600, 5 * 3600, ('N', 'S')), "45°N");
+        assert_eq!(
+            format_angle(-(48 * 3600 + 30 * 60), 60, ('E', 'W')),
+            "48°30'W"
+        );
+        assert_eq!(
+            format_angle(7 * 3600 + 25 * 60 + 5, 5, ('N', 'S')),
+            "7°25'05\"N"
+        );
+    }
+
+    #[test]
+    fn test_graticule_lines() {
+        let lines = graticule_lines(Rect::new(0.0, 0.0, 1.0, 1.0), 90 * 3600);
+        // Meridians -180° to 180°, only the equator is within the world
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0].label.as_deref(), Some("180°W"));
+        assert!((lines[0].from - Point::ZERO).hypot() < 1e-9);
+        assert_eq!(lines[2].label.as_deref(), Some("0°"));
+        assert_eq!(lines[2].from.x, 0.5);
+        assert_eq!(lines[5].label.as_deref(), Some("0°"));
+        assert!(lines[5].from.x == 0.0 && lines[5].to.x == 1.0);
+
+        // A view around Paris, spacing 10'
+        let visible = Rect::from_points(
+            lon_lat_to_world(Point::new(2.2, 48.95)),
+            lon_lat_to_world(Point::new(2.55, 48.75)),
+        );
+        let labels: Vec<String> = graticule_lines(visible, 600)
+            .into_iter()
+            .filter_map(|line| line.label)
+            .collect();
+        assert_eq!(labels, vec!["2°20'E", "2°30'E", "48°50'N"]);
+        assert!(graticule_lines(Rect::new(2.0, 0.0, 3.0, 1.0), 3600).is_empty());
+    }
+
+    #[test]
+    fn test_tile_grid_lines() {
+        let lines = tile_grid_lines(Rect::new(0.3, -1.0, 0.6, 0.2), 3);
+        let xs: Vec<f64> = lines
+            .iter()
+            .filter(|line| line.from.x == line.to.x)
+            .map(|line| line.from.x)
+            .collect();
+        let ys: Vec<f64> = lines
+            .iter()
+            .filter(|line| line.from.y == line.to.y)
+            .map(|line| line.from.y)
+            .collect();
+        assert_eq!(xs, vec![0.375, 0.5]);
+        assert_eq!(ys, vec![0.0, 0.125]);
+        assert!(lines.iter().all(|line| line.from.y >= 0.0));
+
+        assert!(tile_grid_lines(Rect::new(0.0, 0.0, 1.0, 1.0), 12).is_empty());
+        assert!(tile_grid_lines(Rect::new(1.5, 0.0, 2.0, 1.0), 2).is_empty());
+        let tiny = Rect::new(0.5, 0.5, 0.5 + 1e-9, 0.5 + 1e-9);
+        assert!(!tile_grid_lines(tiny, MAX_TILE_GRID_ZOOM).is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, fmt};
+
+use prost::Message;
+
+use crate::{
+    Tile,
+    geometry::{Command, commands},
+    properties::properties,
+    tile::Layer,
+    validate::{Diagnostic, validate_layer},
+};
+
+// Statistics of one layer of a tile, with its specification violations
+pub struct LayerStats {
+    pub name: String,
+    pub version: u32,
+    pub extent: u32,
+    pub bytes: usize,
+    pub keys: usize,
+    pub values: usize,
+    pub features: usize,
+    // Feature count by geometry type
+    pub geometry_types: BTreeMap<&'static str, usize>,
+    // Number of features having each attribute
+    pub key_frequency: BTreeMap<String, usize>,
+    pub vertices: usize,
+    pub violations: Vec<Diagnostic>,
+}
+
+pub fn inspect_tile(data: &[u8]) -> Result<Vec<LayerStats>, String> {
+    let tile = Tile::decode(data).map_err(|e| e.to_string())?;
+    Ok(tile.layers.iter().map(inspect_layer).collect())
+}
+
+fn inspect_layer(layer: &Layer) -> LayerStats {
+    let mut stats = LayerStats {
+        name: layer.name.clone(),
+        version: layer.version,
+        extent: layer.extent(),
+        bytes: layer.encoded_len(),
+        keys: layer.keys.len(),
+        values: layer.values.len(),
+        features: layer.features.len(),
+        geometry_types: BTreeMap::new(),
+        key_frequency: BTreeMap::new(),
+        vertices: 0,
+        violations: validate_layer(layer),
+    };
+    for feature in layer.features.iter() {
+        *stats
+            .geometry_types
+            .entry(feature.r#type().as_str_name())
+            .or_default() += 1;
+        // Broken features are reported by the validator
+        if let Ok(properties) = properties(&layer.keys, &layer.values, &feature.tags) {
+            for (key, _) in properties {
+                *stats.key_frequency.entry(key).or_default() += 1;
+            }
+        }
+        if let Ok(vertices) = vertex_count(&feature.geometry) {
+            stats.vertices += vertices;
+        }
+    }
+    stats
+}
+
+fn vertex_count(geometry: &[u32]) -> Result<usize, String> {
+    commands(geometry).try_fold(0, |count, step| {
+        let (command, _, _) = step?;
+        Ok(count + (command != Command::ClosePath) as usize)
+    })
+}
+
+impl fmt::Display for LayerStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "layer {}: version {}, extent {}, {} bytes",
+            self.name, self.version, self.extent, self.bytes
+        )?;
+        let types: Vec<String> = self
+            .geometry_types
+            .iter()
+            .map(|(t, count)| format!("{} {}", t, count))
+            .collect();
+        writeln!(f, "  features: {} ({})", self.features, types.join(", "))?;
+        writeln!(f, "  vertices: {}", self.vertices)?;
+        writeln!(f, "  keys: {}, values: {}", self.keys, self.values)?;
+
+        let mut keys: Vec<(&String, &usize)> = self.key_frequency.iter().collect();
+        keys.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let keys: Vec<String> = keys
+            .iter()
+            .map(|(key, count)| format!("{} {}", key, count))
+            .collect();
+        writeln!(f, "  attributes: {}", keys.join(", "))?;
+
+        if self.violations.is_empty() {
+            writeln!(f, "  violations: none")
+        } else {
+            writeln!(f, "  violations: {}", self.violations.len())?;
+            for violation in self.violations.iter() {
+                writeln!(f, "    {}", violation)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encoder::{LayerEncoder, encode_tile},
+        geometry::{Coord, TypedGeometry},
+        properties::PropertyValue,
+        validate::Violation,
+    };
+
+    #[test]
+    fn test_inspect_tile() {
+        let mut encoder = LayerEncoder::new("overlay", 512);
+        let line = TypedGeometry::LineString(vec![Coord { x: 0, y: 0 }, Coord { x: 5, y: 5 }]);
+        let properties = [
+            ("name".to_owned(), PropertyValue::String("a".to_owned())),
+            ("rank".to_owned(), PropertyValue::UInt(1)),
+        ];
+        encoder.add_feature(Some(1), &line, &properties);
+        encoder.add_feature(Some(2), &line, &properties[..1]);
+        encoder.add_feature(Some(3), &TypedGeometry::Point(Coord { x: 1, y: 1 }), &[]);
+        let mut tile = encode_tile(vec![encoder]);
+        // Broken on purpose: a dangling key and a LineTo without MoveTo
+        tile.layers[0].features[2].tags = vec![0];
+        tile.layers[0].features[1].geometry = vec![10, 2, 2];
+
+        let stats = inspect_tile(&tile.encode_to_vec()).unwrap();
+        assert_eq!(stats.len(), 1);
+        let stats = &stats[0];
+        assert_eq!((stats.version, stats.extent), (2, 512));
+        assert_eq!((stats.keys, stats.values), (2, 2));
+        assert_eq!(stats.features, 3);
+        assert_eq!(stats.geometry_types["LINESTRING"], 2);
+        assert_eq!(stats.geometry_types["POINT"], 1);
+        assert_eq!(stats.key_frequency["name"], 2);
+        assert_eq!(stats.key_frequency["rank"], 1);
+        assert_eq!(stats.vertices, 3);
+        let violations: Vec<(Option<usize>, Violation)> = stats
+            .violations
+            .iter()
+            .map(|d| (d.feature, d.violation.clone()))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                (Some(1), Violation::FirstCommandNotMoveTo),
+                (Some(2), Violation::OddTagCount(1)),
+            ]
+        );
+    }
+}
@@ -1,14 +1,16 @@
-use core::panic;
-
 use vello::peniko::Color;
 
 use crate::{
-    geometry::Geometry,
-    tile::{Feature, GeomType, Layer},
+    geometry::{Commands, RingIssue, TypedGeometry},
+    properties::{PropertyValue, properties},
+    reader::{FeatureReader, LayerReader, Repeated},
+    tile::{GeomType, Value},
 };
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
 pub enum LayerType {
+    // Layers with names outside the OpenMapTiles schema, drawn on top
+    Other,
     Building,
     Boundary,
     Waterway,
@@ -23,21 +25,46 @@ pub enum LayerType {
     Landuse,
 }
 
-pub struct LayerWrapper {
+// Layer versions this decoder understands, see section 4.1 of the specification
+pub const SUPPORTED_VERSIONS: [u32; 2] = [1, 2];
+
+pub struct LayerWrapper<'a> {
+    name: String,
     layer_type: LayerType,
+    version: u32,
+    extent: u32,
+    keys: Vec<String>,
+    values: Vec<Value>,
 
-    pub features: Vec<FeatureWrapper>,
+    pub features: Vec<FeatureWrapper<'a>>,
 }
 
-impl LayerWrapper {
-    pub fn new(layer: Layer) -> Self {
-        let features = layer
-            .features
-            .iter()
-            .map(|f| FeatureWrapper::new(f.clone()))
-            .collect();
+impl<'a> LayerWrapper<'a> {
+    // Reads the feature headers of a layer, their geometry and properties stay
+    // in the tile data until asked for
+    pub fn new(layer: &LayerReader<'a>) -> Result<Self, String> {
+        let name = layer.name();
+        if !SUPPORTED_VERSIONS.contains(&layer.version()) {
+            return Err(format!(
+                "layer {} has unsupported version {}",
+                name,
+                layer.version()
+            ));
+        }
+        let extent = layer.extent();
+        if extent == 0 {
+            return Err(format!("layer {} has zero extent", name));
+        }
 
-        let layer_type = match layer.name.as_str() {
+        let mut features = Vec::new();
+        for (i, feature) in layer.features().enumerate() {
+            match feature {
+                Ok(reader) => features.push(FeatureWrapper { reader }),
+                Err(e) => log::warn!("layer {}: skipping feature {}: {}", name, i, e),
+            }
+        }
+
+        let layer_type = match name {
             "waterway" => LayerType::Waterway,
             "water" => LayerType::Water,
             "water_name" => LayerType::WaterName,
@@ -50,12 +77,17 @@ impl LayerWrapper {
             "housenumber" => LayerType::Housenumber,
             "poi" => LayerType::Poi,
             "landcover" => LayerType::Landcover,
-            &_ => panic!("{}", layer.name),
+            &_ => LayerType::Other,
         };
-        Self {
+        Ok(Self {
+            name: name.to_owned(),
             layer_type,
+            version: layer.version(),
+            extent,
+            keys: layer.keys().iter().map(|&k| k.to_owned()).collect(),
+            values: layer.values()?,
             features,
-        }
+        })
     }
 
     pub fn color(&self) -> Color {
@@ -73,29 +105,51 @@ impl LayerWrapper {
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn extent(&self) -> u32 {
+        self.extent
+    }
+
     pub fn layer_type(&self) -> LayerType {
         self.layer_type.clone()
     }
+
+    pub fn properties(
+        &self,
+        feature: &FeatureWrapper,
+    ) -> Result<Vec<(String, PropertyValue)>, String> {
+        let tags: Vec<u32> = feature.reader.tags().collect::<Result<_, _>>()?;
+        properties(&self.keys, &self.values, &tags)
+    }
 }
 
-pub struct FeatureWrapper {
-    geometry: Geometry,
-    feature: Feature,
+// A feature borrowed from the tile data. Its geometry is decoded on every
+// call to `geometry`, its properties by LayerWrapper::properties.
+pub struct FeatureWrapper<'a> {
+    reader: FeatureReader<'a>,
 }
 
-impl FeatureWrapper {
-    pub fn new(feature: Feature) -> Self {
-        Self {
-            geometry: Geometry::try_from(&feature.geometry).unwrap(),
-            feature,
-        }
+impl<'a> FeatureWrapper<'a> {
+    pub fn id(&self) -> Option<u64> {
+        self.reader.id()
     }
 
     pub fn ftype(&self) -> GeomType {
-        self.feature.r#type()
+        self.reader.geom_type()
+    }
+
+    pub fn commands(&self) -> Commands<Repeated<'a>> {
+        Commands::new(self.reader.geometry())
     }
 
-    pub fn geometry(&self) -> &Geometry {
-        &self.geometry
+    pub fn geometry(&self) -> Result<(TypedGeometry, Vec<RingIssue>), String> {
+        TypedGeometry::decode(self.commands(), self.ftype())
     }
 }
@@ -0,0 +1,37 @@
+pub mod camera;
+pub mod clip;
+pub mod debug_overlay;
+pub mod decoded_tile;
+pub mod encoder;
+pub mod export;
+pub mod geojson;
+pub mod geometry;
+pub mod graticule;
+pub mod inspect;
+pub mod layer_wrapper;
+pub mod overlay;
+pub mod path;
+pub mod projection;
+pub mod properties;
+pub mod reader;
+pub mod render;
+pub mod simplify;
+pub mod spatial_index;
+pub mod text;
+pub mod tile_cache;
+pub mod tile_id;
+pub mod tile_loader;
+pub mod tile_source;
+pub mod tile_writer;
+pub mod tiler;
+pub mod validate;
+
+use decoded_tile::DecodedTile;
+use tile_id::TileId;
+
+// Sent to the viewer's event loop
+pub enum UserEvent {
+    TileLoaded(TileId, Result<DecodedTile, String>),
+}
+
+include!(concat!(env!("OUT_DIR"), "/vector_tile.rs"));
@@ -1,20 +1,41 @@
-mod geometry;
-mod layer_wrapper;
-mod path;
-
-use geometry::{Command, Geometry, Operation};
-use layer_wrapper::LayerWrapper;
-use path::{
-    Path,
-    PathType::{Fill, StrokeLine},
+use mapstick::{
+    Tile, UserEvent,
+    camera::Camera,
+    debug_overlay::{FrameStats, draw_stats, draw_tile_debug},
+    decoded_tile::DecodedTile,
+    export::{feature_collection, layer_features},
+    geojson::read_features,
+    graticule::{MAX_TILE_GRID_ZOOM, draw_graticule, draw_tile_grid},
+    inspect::inspect_tile,
+    overlay::{Compass, Status, draw_scale_bars, draw_status_bar},
+    path::STROKE_WIDTH,
+    projection::{ground_resolution, world_to_lon_lat},
+    render::{Appended, append_fallback, append_tile},
+    tile_cache::TileCache,
+    tile_id::TileId,
+    tile_loader::{MAX_OVERZOOM, TileLoader, decode_layers},
+    tile_source::{TileSource, decompress},
+    tile_writer::TileWriter,
+    tiler::{TilerOptions, write_tiles},
+    validate::{Severity, validate_tile},
 };
 use prost::Message;
-use std::{cell::RefCell, collections::BinaryHeap, io::Read, num::NonZeroUsize, sync::Arc};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
 use vello::{
     Renderer, RendererOptions, Scene,
-    kurbo::{Affine, Point, Vec2},
-    peniko::{self, color::AlphaColor},
+    kurbo::{Point, Vec2},
+    peniko::color::AlphaColor,
     util::{RenderContext, RenderSurface},
 };
 use winit::{
@@ -22,7 +43,7 @@ use winit::{
     dpi::LogicalSize,
     event::{TouchPhase, WindowEvent},
     event_loop::{self, EventLoop},
-    keyboard::NamedKey,
+    keyboard::{Key, NamedKey},
     window::{Window, WindowAttributes},
 };
 
@@ -39,35 +60,59 @@ struct App<'app> {
     context: RenderContext,
     renderers: Vec<Option<Renderer>>,
     scene: Scene,
-    paths: BinaryHeap<RefCell<Path>>,
+    loader: TileLoader,
+    zoom_range: (u8, u8),
+    tiles: TileCache,
+    // When recently loaded tiles arrived, they fade in over what was drawn in their place
+    fade_ins: HashMap<TileId, Instant>,
+    camera: Camera,
     drag_pos_x: f64,
     drag_pos_y: f64,
     mouse_pos_x: f64,
     mouse_pos_y: f64,
     mouse_pressed: bool,
+    // Whether tile borders and frame statistics are drawn
+    debug: bool,
+    graticule: bool,
+    // Zoom level of the tile grid to draw, if any
+    tile_grid: Option<u8>,
+    frame_time: Duration,
 }
 
 const WIDTH: u32 = 2000;
 const HEIGHT: u32 = 2000;
+const TILE_CACHE_BUDGET: usize = 256 * 1024 * 1024;
+const FADE_DURATION: Duration = Duration::from_millis(300);
+// Zoomed out further below the source's minimum zoom, nothing is drawn: every
+// level takes four times as many tiles to cover the view
+const MAX_UNDERZOOM: u8 = 2;
 
 impl<'app> App<'app> {
-    fn new(paths: BinaryHeap<RefCell<Path>>) -> App<'app> {
+    fn new(loader: TileLoader, zoom_range: (u8, u8), camera: Camera) -> App<'app> {
         Self {
             app_state: AppState::Suspended(None),
             context: RenderContext::new(),
             renderers: vec![],
             scene: Scene::new(),
-            paths,
+            loader,
+            zoom_range,
+            tiles: TileCache::new(TILE_CACHE_BUDGET),
+            fade_ins: HashMap::new(),
+            camera,
             drag_pos_x: 0.0,
             drag_pos_y: 0.0,
             mouse_pos_x: 0.0,
             mouse_pos_y: 0.0,
             mouse_pressed: false,
+            debug: false,
+            graticule: false,
+            tile_grid: None,
+            frame_time: Duration::ZERO,
         }
     }
 }
 
-impl<'app> ApplicationHandler for App<'app> {
+impl<'app> ApplicationHandler<UserEvent> for App<'app> {
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
@@ -94,7 +139,15 @@ impl<'app> ApplicationHandler for App<'app> {
                 state,
                 button: _,
             } => {
-                if state.is_pressed() {
+                let width = surface.config.width as f64;
+                let height = surface.config.height as f64;
+                let compass = Compass::new(width, overlay_pixel(window));
+                let mouse_pos = Point::new(self.mouse_pos_x, self.mouse_pos_y);
+                if state.is_pressed() && compass.contains(mouse_pos) {
+                    self.camera
+                        .reset_bearing(Point::new(width / 2.0, height / 2.0));
+                    window.request_redraw();
+                } else if state.is_pressed() {
                     self.mouse_pressed = true;
                 } else {
                     self.mouse_pressed = false;
@@ -111,14 +164,10 @@ impl<'app> ApplicationHandler for App<'app> {
                     if self.drag_pos_y == 0.0 {
                         self.drag_pos_y = position.y;
                     }
-                    for path in self.paths.iter() {
-                        path.borrow_mut()
-                            .bez_path
-                            .apply_affine(Affine::translate(Vec2::new(
-                                position.x - self.drag_pos_x,
-                                position.y - self.drag_pos_y,
-                            )));
-                    }
+                    self.camera.pan(Vec2::new(
+                        position.x - self.drag_pos_x,
+                        position.y - self.drag_pos_y,
+                    ));
                     self.drag_pos_x = position.x;
                     self.drag_pos_y = position.y;
                     window.request_redraw();
@@ -127,6 +176,8 @@ impl<'app> ApplicationHandler for App<'app> {
                     self.drag_pos_y = 0.0;
                     self.mouse_pos_x = position.x;
                     self.mouse_pos_y = position.y;
+                    // The status bar follows the cursor
+                    window.request_redraw();
                 }
             }
             WindowEvent::PinchGesture {
@@ -135,57 +186,67 @@ impl<'app> ApplicationHandler for App<'app> {
                 phase,
             } => {
                 if phase == TouchPhase::Moved {
-                    for path in self.paths.iter() {
-                        path.borrow_mut()
-                            .bez_path
-                            .apply_affine(Affine::scale(1.0 + delta));
-                        path.borrow_mut()
-                            .bez_path
-                            .apply_affine(Affine::translate(Vec2::new(
-                                -1.0 * (self.mouse_pos_x * delta),
-                                -1.0 * (self.mouse_pos_y * delta),
-                            )));
-                    }
+                    self.camera
+                        .zoom_at(Point::new(self.mouse_pos_x, self.mouse_pos_y), 1.0 + delta);
                     window.request_redraw();
                 }
             }
+            WindowEvent::RotationGesture {
+                device_id: _,
+                delta,
+                phase: TouchPhase::Moved,
+            } => {
+                let center = Point::new(
+                    surface.config.width as f64 / 2.0,
+                    surface.config.height as f64 / 2.0,
+                );
+                self.camera.rotate_at(center, delta as f64);
+                window.request_redraw();
+            }
             WindowEvent::KeyboardInput { event, .. } => {
                 if event.state.is_pressed() {
                     let move_step = 50.0;
+                    let rotate_step = 15.0;
                     if event.logical_key == NamedKey::Escape {
                         log::info!("exiting on ESC");
                         event_loop.exit();
                     };
                     if event.logical_key == NamedKey::ArrowDown {
-                        for path in self.paths.iter() {
-                            path.borrow_mut()
-                                .bez_path
-                                .apply_affine(Affine::translate(Vec2::new(0.0, -move_step)));
-                        }
+                        self.camera.pan(Vec2::new(0.0, -move_step));
                         window.request_redraw();
                     }
                     if event.logical_key == NamedKey::ArrowRight {
-                        for path in self.paths.iter() {
-                            path.borrow_mut()
-                                .bez_path
-                                .apply_affine(Affine::translate(Vec2::new(-move_step, 0.0)));
-                        }
+                        self.camera.pan(Vec2::new(-move_step, 0.0));
                         window.request_redraw();
                     }
                     if event.logical_key == NamedKey::ArrowUp {
-                        for path in self.paths.iter() {
-                            path.borrow_mut()
-                                .bez_path
-                                .apply_affine(Affine::translate(Vec2::new(0.0, move_step)));
-                        }
+                        self.camera.pan(Vec2::new(0.0, move_step));
                         window.request_redraw();
                     }
                     if event.logical_key == NamedKey::ArrowLeft {
-                        for path in self.paths.iter() {
-                            path.borrow_mut()
-                                .bez_path
-                                .apply_affine(Affine::translate(Vec2::new(move_step, 0.0)));
-                        }
+                        self.camera.pan(Vec2::new(move_step, 0.0));
+                        window.request_redraw();
+                    }
+                    if event.logical_key.as_ref() == Key::Character("g") {
+                        self.graticule = !self.graticule;
+                        window.request_redraw();
+                    }
+                    if event.logical_key.as_ref() == Key::Character("d") {
+                        self.debug = !self.debug;
+                        window.request_redraw();
+                    }
+                    // Q and E turn the map around the center of the window
+                    let rotation = match event.logical_key.as_ref() {
+                        Key::Character("q") => Some(rotate_step),
+                        Key::Character("e") => Some(-rotate_step),
+                        _ => None,
+                    };
+                    if let Some(degrees) = rotation {
+                        let center = Point::new(
+                            surface.config.width as f64 / 2.0,
+                            surface.config.height as f64 / 2.0,
+                        );
+                        self.camera.rotate_at(center, degrees);
                         window.request_redraw();
                     }
                 }
@@ -196,24 +257,189 @@ impl<'app> ApplicationHandler for App<'app> {
             }
             WindowEvent::RedrawRequested => {
                 log::trace!("redraw requested");
+                let frame_start = Instant::now();
+
+                let width = surface.config.width;
+                let height = surface.config.height;
 
                 self.scene.reset();
-                let mut paths2 = BinaryHeap::new();
-                loop {
-                    let Some(path) = self.paths.pop() else {
-                        break;
+                // Strokes reach half their width outside of path bounding boxes
+                let margin = STROKE_WIDTH / 2.0 / self.camera.scale();
+                let visible = self
+                    .camera
+                    .visible_rect(width as f64, height as f64)
+                    .inflate(margin, margin);
+                let z = tile_level(&self.camera, self.zoom_range);
+                let wanted = z.map_or(vec![], |z| TileId::covering(visible, z));
+                self.loader.retain(&wanted);
+                // Tiles that went out of view mid-fade are drawn at once when back
+                self.fade_ins.retain(|id, _| wanted.contains(id));
+
+                let now = Instant::now();
+                let mut stats = FrameStats {
+                    frame_time: self.frame_time,
+                    ..FrameStats::default()
+                };
+                let mut appended = Appended::default();
+                let mut fading = false;
+                for id in &wanted {
+                    let fade = self.fade_ins.get(id).map(|start| {
+                        now.duration_since(*start).as_secs_f64() / FADE_DURATION.as_secs_f64()
+                    });
+                    let alpha = match fade {
+                        Some(fade) if fade < 1.0 => fade as f32,
+                        Some(_) => {
+                            self.fade_ins.remove(id);
+                            1.0
+                        }
+                        None => 1.0,
                     };
-                    path.borrow().draw(&mut self.scene);
-                    paths2.push(path);
+                    if alpha < 1.0 {
+                        appended += append_fallback(
+                            &mut self.scene,
+                            &self.camera,
+                            &mut self.tiles,
+                            *id,
+                            visible,
+                        );
+                        fading = true;
+                    }
+
+                    let Some(tile) = self.tiles.get(*id) else {
+                        if self.loader.request(*id) {
+                            self.tiles.count_miss();
+                        }
+                        let fallback = append_fallback(
+                            &mut self.scene,
+                            &self.camera,
+                            &mut self.tiles,
+                            *id,
+                            visible,
+                        );
+                        if fallback.tiles > 0 {
+                            stats.fallback_tiles += 1;
+                        }
+                        appended += fallback;
+                        continue;
+                    };
+                    // Tiles carry a buffer of geometry outside of their bounds
+                    if !tile
+                        .bounds()
+                        .is_some_and(|b| id.transform().transform_rect_bbox(b).overlaps(visible))
+                    {
+                        stats.culled_tiles += 1;
+                        appended.culled_paths += tile.paths.len();
+                        continue;
+                    }
+                    appended += append_tile(
+                        &mut self.scene,
+                        &self.camera,
+                        *id,
+                        tile,
+                        None,
+                        alpha,
+                        visible,
+                    );
+                    stats.drawn_tiles += 1;
+                }
+                stats.paths = appended.paths;
+                stats.culled_paths = appended.culled_paths;
+                stats.vertices = appended.vertices;
+                if fading {
+                    window.request_redraw();
+                }
+                self.tiles.end_frame(visible.center());
+                let cache_stats = self.tiles.stats();
+                stats.pending_tiles = self.loader.pending_count();
+                stats.cache = Some(cache_stats);
+                log::info!(
+                    "frame: drew {} tiles ({} paths), culled {} tiles and {} paths, {} tiles pending ({} with fallbacks), cache: {} tiles, {} of {} KiB, {} hits, {} misses ({:.0}%)",
+                    stats.drawn_tiles,
+                    stats.paths,
+                    stats.culled_tiles,
+                    stats.culled_paths,
+                    stats.pending_tiles,
+                    stats.fallback_tiles,
+                    cache_stats.tiles,
+                    cache_stats.bytes / 1024,
+                    cache_stats.budget / 1024,
+                    cache_stats.hits,
+                    cache_stats.misses,
+                    cache_stats.hit_rate() * 100.0
+                );
+
+                let status = Status {
+                    cursor: self
+                        .camera
+                        .world_point(Point::new(self.mouse_pos_x, self.mouse_pos_y)),
+                    zoom: self.camera.tile_zoom(),
+                    tile_zoom: z.unwrap_or(self.zoom_range.0),
+                    scale: self.camera.scale(),
+                    scale_factor: window.scale_factor(),
+                };
+                let pixel = overlay_pixel(window);
+                if let Some(z) = self.tile_grid {
+                    draw_tile_grid(
+                        &mut self.scene,
+                        &self.camera,
+                        width as f64,
+                        height as f64,
+                        z,
+                        pixel,
+                    );
+                }
+                if self.graticule {
+                    draw_graticule(
+                        &mut self.scene,
+                        &self.camera,
+                        width as f64,
+                        height as f64,
+                        pixel,
+                    );
+                }
+                if self.debug {
+                    for id in &wanted {
+                        draw_tile_debug(
+                            &mut self.scene,
+                            self.camera.transform(),
+                            *id,
+                            self.loader.buffer(),
+                            pixel,
+                        );
+                    }
+                    let margin = 4.0 * pixel;
+                    draw_stats(
+                        &mut self.scene,
+                        &stats.lines(),
+                        Point::new(margin, margin),
+                        pixel,
+                    );
                 }
-                self.paths.append(&mut paths2);
+                let status_bar = draw_status_bar(
+                    &mut self.scene,
+                    &status.text(),
+                    width as f64,
+                    height as f64,
+                    pixel,
+                );
+                let center = self
+                    .camera
+                    .world_point(Point::new(width as f64 / 2.0, height as f64 / 2.0));
+                let meters_per_pixel =
+                    ground_resolution(world_to_lon_lat(center).y, self.camera.scale());
+                let margin = 4.0 * pixel;
+                draw_scale_bars(
+                    &mut self.scene,
+                    meters_per_pixel,
+                    Point::new(margin, status_bar.y0 - margin),
+                    pixel,
+                );
+                Compass::new(width as f64, pixel).draw(&mut self.scene, self.camera.bearing());
 
                 let dev_id = surface.dev_id;
                 let device_handle = &self.context.devices[dev_id];
                 let device = &device_handle.device;
                 let queue = &device_handle.queue;
-                let width = surface.config.width;
-                let height = surface.config.height;
                 let texture = surface.surface.get_current_texture().unwrap();
 
                 let params = &vello::RenderParams {
@@ -231,14 +457,30 @@ impl<'app> ApplicationHandler for App<'app> {
 
                 // IDK:
                 texture.present();
-                // IDK:
-                // println!("{:?}", device_handle.adapter().features());
                 device_handle.device.poll(vello::wgpu::MaintainBase::Poll);
+                self.frame_time = frame_start.elapsed();
             }
             _ => (),
         }
     }
 
+    fn user_event(&mut self, _event_loop: &event_loop::ActiveEventLoop, event: UserEvent) {
+        match event {
+            UserEvent::TileLoaded(id, result) => {
+                self.loader.finish(id);
+                let tile = result.unwrap_or_else(|e| {
+                    log::error!("failed to load tile {}: {}", id, e);
+                    DecodedTile::empty()
+                });
+                self.tiles.insert(id, tile);
+                self.fade_ins.insert(id, Instant::now());
+                if let AppState::Active { window, .. } = &self.app_state {
+                    window.request_redraw();
+                }
+            }
+        }
+    }
+
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         log::info!("resumed");
         let AppState::Suspended(ref mut cached_window) = self.app_state else {
@@ -293,93 +535,285 @@ impl<'app> ApplicationHandler for App<'app> {
     }
 }
 
-fn create_path(geometry: &Geometry) -> vello::kurbo::BezPath {
-    let mut path = peniko::kurbo::BezPath::new();
-    let mut px = 0.0;
-    let mut py = 0.0;
-    for operation in geometry.operations.iter() {
-        match operation {
-            Operation {
-                command: Command::MoveTo,
-                params,
-            } => {
-                let cx = px + params.get(0).unwrap().raw_value as f64;
-                let cy = py + params.get(1).unwrap().raw_value as f64;
-                path.move_to(Point::new(cx, cy));
-                px = cx;
-                py = cy;
-            }
-            Operation {
-                command: Command::LineTo,
-                params,
-            } => {
-                let cx = px + params.get(0).unwrap().raw_value as f64;
-                let cy = py + params.get(1).unwrap().raw_value as f64;
-                path.line_to(Point::new(cx, cy));
-                px = cx;
-                py = cy;
-            }
-            Operation {
-                command: Command::ClosePath,
-                ..
-            } => {
-                path.close_path();
-            }
-        }
-    }
+// Command line tools run instead of the viewer, given the remaining arguments
+type Subcommand = fn(&mut dyn Iterator<Item = String>) -> Result<(), String>;
 
-    path
+// Size of a font pixel of the overlays in screen pixels
+fn overlay_pixel(window: &Window) -> f64 {
+    2.0 * window.scale_factor()
+}
+
+// Zoom level of the tiles to draw at the scale of `camera`, None if zoomed
+// out more than MAX_UNDERZOOM levels below the source's minimum zoom
+fn tile_level(camera: &Camera, zoom_range: (u8, u8)) -> Option<u8> {
+    let (min_zoom, max_zoom) = zoom_range;
+    let z = camera.tile_zoom().round();
+    if z < min_zoom as f64 - MAX_UNDERZOOM as f64 {
+        return None;
+    }
+    Some(z.clamp(min_zoom as f64, max_zoom.max(MAX_OVERZOOM) as f64) as u8)
 }
 
 fn main() {
     env_logger::builder()
         .format_timestamp(Some(env_logger::TimestampPrecision::Millis))
         .filter_level(log::LevelFilter::Info)
+        // RUST_LOG, if set, overrides the level, e.g. to hide frame stats
+        .parse_default_env()
         .init();
 
-    let mut paths = BinaryHeap::new();
-    let layer_wrappers = get_layers();
-    for layer_wrapper in layer_wrappers {
-        for feature in &layer_wrapper.features {
-            match feature.ftype() {
-                tile::GeomType::Unknown => (),
-                tile::GeomType::Point => (),
-                tile::GeomType::Linestring => paths.push(RefCell::new(Path::new(
-                    create_path(feature.geometry()),
-                    layer_wrapper.color(),
-                    StrokeLine,
-                    layer_wrapper.layer_type(),
-                ))),
-                tile::GeomType::Polygon => paths.push(RefCell::new(Path::new(
-                    create_path(feature.geometry()),
-                    layer_wrapper.color(),
-                    Fill,
-                    layer_wrapper.layer_type(),
-                ))),
-            }
+    let mut args = std::env::args().skip(1).peekable();
+    let command: Option<Subcommand> = match args.peek().map(String::as_str) {
+        Some("tile") => Some(tile_command),
+        Some("export") => Some(export_command),
+        Some("inspect") => Some(inspect_command),
+        Some("validate") => Some(validate_command),
+        _ => None,
+    };
+    if let Some(command) = command {
+        args.next();
+        if let Err(e) = command(&mut args) {
+            log::error!("{}", e);
+            std::process::exit(1);
         }
+        return;
     }
 
-    let event_loop = EventLoop::new().unwrap();
-    let mut app = App::new(paths);
+    let exit = |e: String| -> ! {
+        log::error!("{}", e);
+        std::process::exit(1);
+    };
+    let options = viewer_options(&mut args).unwrap_or_else(|e| exit(e));
+    let (source, first_tile) = viewer_source(&options.positional).unwrap_or_else(|e| exit(e));
+    let zoom_range = source.zoom_range();
+    let camera = Camera::showing(first_tile);
+
+    let event_loop = EventLoop::with_user_event().build().unwrap();
+    let workers = thread::available_parallelism().map_or(2, |n| n.get());
+    let proxy = event_loop.create_proxy();
+    let loader = TileLoader::new(source, options.buffer, options.layers, proxy, workers);
+    let mut app = App::new(loader, zoom_range, camera);
+    app.graticule = options.graticule;
+    app.tile_grid = options.tile_grid;
     let _ = event_loop.run_app(&mut app);
 }
 
-include!(concat!(env!("OUT_DIR"), "/vector_tile.rs"));
+#[derive(Default)]
+struct ViewerOptions {
+    buffer: f64,
+    layers: Option<HashSet<String>>,
+    graticule: bool,
+    tile_grid: Option<u8>,
+    positional: Vec<String>,
+}
+
+// mapstick [--buffer FRACTION] [--layers NAME,...] [--graticule]
+// [--tile-grid Z] [PATH] [Z/X/Y]: a tile directory tree, or a single tile
+// file shown as tile Z/X/Y. Tile geometry is clipped to the tile bounds
+// grown by FRACTION of the tile width. With --layers, only the named
+// layers are decoded and drawn. --graticule starts with latitude and
+// longitude lines shown, --tile-grid draws the edges of zoom level Z tiles.
+fn viewer_options(args: &mut dyn Iterator<Item = String>) -> Result<ViewerOptions, String> {
+    let mut options = ViewerOptions::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--buffer" => options.buffer = number(value()?)?,
+            "--layers" => {
+                let names = value()?;
+                options.layers = Some(names.split(',').map(str::to_owned).collect());
+            }
+            "--graticule" => options.graticule = true,
+            "--tile-grid" => {
+                let z = number(value()?)?;
+                if z > MAX_TILE_GRID_ZOOM {
+                    return Err(format!("wrong tile grid zoom {}", z));
+                }
+                options.tile_grid = Some(z);
+            }
+            _ => options.positional.push(arg),
+        }
+    }
+    Ok(options)
+}
+
+// The source given by the viewer's positional arguments, tile1.mvt by
+// default, and the tile to start on
+fn viewer_source(positional: &[String]) -> Result<(TileSource, TileId), String> {
+    let (path, id) = match positional {
+        [] => ("tile1.mvt", TileId::new(0, 0, 0)),
+        _ => tile_arguments(positional, "usage: mapstick [OPTIONS] [PATH] [Z/X/Y]")?,
+    };
+    let source = TileSource::open(PathBuf::from(path), id)?;
+    let first_tile = source.first_tile()?;
+    Ok((source, first_tile))
+}
+
+// mapstick tile [--min-zoom Z] [--max-zoom Z] [--layer NAME] [--extent N]
+// [--buffer FRACTION] [--tolerance UNITS] INPUT OUTPUT: slices a GeoJSON file
+// into vector tiles, written to OUTPUT as a directory tree or, if it ends in
+// .mbtiles, as an MBTiles archive
+fn tile_command(args: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let mut options = TilerOptions::default();
+    let mut positional = vec![];
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--min-zoom" => options.min_zoom = number(value()?)?,
+            "--max-zoom" => options.max_zoom = number(value()?)?,
+            "--layer" => options.layer = value()?,
+            "--extent" => options.extent = number(value()?)?,
+            "--buffer" => options.buffer = number(value()?)?,
+            "--tolerance" => options.tolerance = number(value()?)?,
+            _ => positional.push(arg),
+        }
+    }
+    let [input, output] = positional.as_slice() else {
+        return Err("usage: mapstick tile [OPTIONS] INPUT OUTPUT".to_owned());
+    };
+    if options.min_zoom > options.max_zoom || options.max_zoom > MAX_OVERZOOM {
+        return Err(format!(
+            "wrong zoom range {}..={}",
+            options.min_zoom, options.max_zoom
+        ));
+    }
+    if options.extent == 0 {
+        return Err("wrong extent 0".to_owned());
+    }
+
+    let text = fs::read_to_string(input).map_err(|e| format!("{}: {}", input, e))?;
+    let features = read_features(&text).map_err(|e| format!("{}: {}", input, e))?;
+    let writer = TileWriter::create(Path::new(output))?;
+    let count = write_tiles(&features, &options, writer)?;
+    log::info!(
+        "{} features written to {} tiles in {}",
+        features.len(),
+        count,
+        output
+    );
+    Ok(())
+}
+
+// mapstick export [--merge] [--output PATH] TILE [Z/X/Y]: writes the layers
+// of a tile as GeoJSON, one PATH/<layer>.geojson file per layer (PATH
+// defaults to the current directory) or, with --merge, a single collection
+// to PATH or standard output. TILE is a tile file georeferenced as Z/X/Y, or
+// a directory tree or MBTiles archive to read tile Z/X/Y from.
+fn export_command(args: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let mut merge = false;
+    let mut output = None;
+    let mut positional = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--merge" => merge = true,
+            "--output" => {
+                output = Some(PathBuf::from(args.next().ok_or("--output needs a value")?))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let usage = "usage: mapstick export [--merge] [--output PATH] TILE [Z/X/Y]";
+    let (path, id) = tile_arguments(&positional, usage)?;
+
+    let data = read_tile(path, id)?;
+    let layers = decode_layers(&data, None)?;
+
+    let write = |path: Option<&Path>, collection: Value| match path {
+        Some(path) => fs::write(path, collection.to_string())
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        None => {
+            println!("{}", collection);
+            Ok(())
+        }
+    };
+    if merge {
+        let features = layers
+            .iter()
+            .flat_map(|layer| layer_features(layer, id, true))
+            .collect();
+        return write(output.as_deref(), feature_collection(features));
+    }
+
+    let dir = output.unwrap_or_default();
+    if !dir.as_os_str().is_empty() {
+        fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    for layer in layers.iter() {
+        let file = dir.join(format!("{}.geojson", layer.name()));
+        write(
+            Some(&file),
+            feature_collection(layer_features(layer, id, false)),
+        )?;
+        log::info!(
+            "layer {}: {} features written to {}",
+            layer.name(),
+            layer.features.len(),
+            file.display()
+        );
+    }
+    Ok(())
+}
+
+// mapstick inspect TILE [Z/X/Y]: prints statistics and specification
+// violations of every layer of a tile, read like in `export`
+fn inspect_command(args: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let positional: Vec<String> = args.collect();
+    let (path, id) = tile_arguments(&positional, "usage: mapstick inspect TILE [Z/X/Y]")?;
 
-pub fn get_layers() -> Vec<LayerWrapper> {
-    let mut file = std::fs::File::open("tile1.mvt").unwrap();
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf).unwrap();
-    let tile = Tile::decode(buf.as_slice()).unwrap();
+    let data = read_tile(path, id)?;
+    let layers = inspect_tile(&data)?;
+    println!("tile {}: {} layers, {} bytes", id, layers.len(), data.len());
+    for layer in layers {
+        print!("{}", layer);
+    }
+    Ok(())
+}
+
+// mapstick validate TILE [Z/X/Y]: checks a tile, read like in `export`,
+// against version 2.1 of the specification and fails if it finds errors
+fn validate_command(args: &mut dyn Iterator<Item = String>) -> Result<(), String> {
+    let positional: Vec<String> = args.collect();
+    let (path, id) = tile_arguments(&positional, "usage: mapstick validate TILE [Z/X/Y]")?;
 
-    let mut res = Vec::new();
-    // for (i, layer) in tile.layers.iter().enumerate() {
-    //     std::fs::write(format!("layer{i}.txt"), format!("{:#?}", layer)).unwrap();
-    // }
-    for layer in tile.layers {
-        res.push(LayerWrapper::new(layer));
+    let tile = Tile::decode(read_tile(path, id)?.as_slice()).map_err(|e| e.to_string())?;
+    let diagnostics = validate_tile(&tile);
+    for diagnostic in diagnostics.iter() {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.violation.severity() == Severity::Error)
+        .count();
+    println!(
+        "tile {}: {} errors, {} warnings",
+        id,
+        errors,
+        diagnostics.len() - errors
+    );
+    if errors > 0 {
+        return Err(format!("{}: tile {} is invalid", path, id));
     }
+    Ok(())
+}
+
+// TILE [Z/X/Y] arguments, the tile id defaults to 0/0/0
+fn tile_arguments<'a>(positional: &'a [String], usage: &str) -> Result<(&'a str, TileId), String> {
+    match positional {
+        [path] => Ok((path, TileId::new(0, 0, 0))),
+        [path, id] => Ok((path, id.parse()?)),
+        _ => Err(usage.to_owned()),
+    }
+}
+
+// Decompressed data of tile `id` of a tile file, directory tree or archive
+fn read_tile(path: &str, id: TileId) -> Result<Vec<u8>, String> {
+    let source = TileSource::open(PathBuf::from(path), id)?;
+    let data = source
+        .read(id)?
+        .ok_or(format!("{}: no tile {}", path, id))?;
+    decompress(data)
+}
 
-    res
+fn number<T: FromStr>(value: String) -> Result<T, String> {
+    value.parse().map_err(|_| format!("wrong number {}", value))
 }
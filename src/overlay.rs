@@ -0,0 +1,345 @@
+use vello::{
+    Scene,
+    kurbo::{Affine, BezPath, Circle, Point, Rect, Shape},
+    peniko::{Color, Fill},
+};
+
+use crate::{
+    projection::{ground_resolution, tile_for_world, world_to_lon_lat},
+    text::{draw_text, text_height, text_path, text_width},
+};
+
+// Size of a screen pixel assumed by map scales, see the OGC WMS specification
+const STANDARD_PIXEL_SIZE: f64 = 0.000_28;
+
+const METERS_PER_FOOT: f64 = 0.3048;
+const FEET_PER_MILE: f64 = 5280.0;
+
+// Longest a scale bar gets, in font pixels
+const SCALE_BAR_LENGTH: f64 = 60.0;
+
+// In font pixels
+const COMPASS_RADIUS: f64 = 16.0;
+const COMPASS_MARGIN: f64 = 4.0;
+
+pub const OVERLAY_TEXT: Color = Color::new([1.0, 1.0, 1.0, 1.0]);
+pub const OVERLAY_BACKGROUND: Color = Color::new([0.0, 0.0, 0.0, 0.6]);
+const COMPASS_NORTH: Color = Color::new([0.9, 0.15, 0.1, 1.0]);
+
+// What the status bar shows about the point under the cursor
+pub struct Status {
+    // In world coordinates
+    pub cursor: Point,
+    // Fractional zoom level of the view
+    pub zoom: f64,
+    // Zoom level of the tiles drawn
+    pub tile_zoom: u8,
+    // Screen pixels per world width
+    pub scale: f64,
+    // Physical pixels per logical pixel of the window
+    pub scale_factor: f64,
+}
+
+impl Status {
+    pub fn text(&self) -> String {
+        let in_world = |v: f64| (0.0..=1.0).contains(&v);
+        if !(in_world(self.cursor.x) && in_world(self.cursor.y)) {
+            // Off the map, where the projection has no meaning
+            return format!("LON -  LAT -  ZOOM {:.2}  TILE -  SCALE -", self.zoom);
+        }
+        let lon_lat = world_to_lon_lat(self.cursor);
+        let hemisphere = |v: f64, positive, negative| if v < 0.0 { negative } else { positive };
+        let tile = tile_for_world(self.cursor, self.tile_zoom);
+        // Scale of logical pixels, as those have the standard size
+        let meters_per_pixel = ground_resolution(lon_lat.y, self.scale) * self.scale_factor;
+        let denominator = (meters_per_pixel / STANDARD_PIXEL_SIZE).round() as u64;
+        format!(
+            "LON {:.5}° {}  LAT {:.5}° {}  ZOOM {:.2}  TILE {}  SCALE 1:{}",
+            lon_lat.x.abs(),
+            hemisphere(lon_lat.x, 'E', 'W'),
+            lon_lat.y.abs(),
+            hemisphere(lon_lat.y, 'N', 'S'),
+            self.zoom,
+            tile,
+            group_thousands(denominator)
+        )
+    }
+}
+
+// Draws a bar along the bottom of a width x height window, `pixel` is the
+// size of a font pixel in screen pixels. Returns the bar.
+pub fn draw_status_bar(scene: &mut Scene, text: &str, width: f64, height: f64, pixel: f64) -> Rect {
+    let padding = 2.0 * pixel;
+    let bar_height = text_height(pixel) + 2.0 * padding;
+    let bar = Rect::new(0.0, height - bar_height, width, height);
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        OVERLAY_BACKGROUND,
+        None,
+        &bar,
+    );
+    let origin = Point::new(padding, bar.y0 + padding);
+    draw_text(scene, text, origin, pixel, OVERLAY_TEXT);
+    bar
+}
+
+// A round distance and how long it is on screen
+#[derive(Debug, PartialEq)]
+pub struct ScaleBar {
+    // In screen pixels
+    pub length: f64,
+    pub label: String,
+}
+
+// The longest round distance in meters or kilometers fitting `max_length`
+// screen pixels
+pub fn metric_scale_bar(meters_per_pixel: f64, max_length: f64) -> ScaleBar {
+    let meters = round_down(meters_per_pixel * max_length);
+    let label = if meters >= 1000.0 {
+        format!("{} km", meters / 1000.0)
+    } else {
+        format!("{} m", meters)
+    };
+    ScaleBar {
+        length: meters / meters_per_pixel,
+        label,
+    }
+}
+
+// Same in feet or miles
+pub fn imperial_scale_bar(meters_per_pixel: f64, max_length: f64) -> ScaleBar {
+    let feet = meters_per_pixel * max_length / METERS_PER_FOOT;
+    let (feet, label) = if feet >= FEET_PER_MILE {
+        let miles = round_down(feet / FEET_PER_MILE);
+        (miles * FEET_PER_MILE, format!("{} mi", miles))
+    } else {
+        let feet = round_down(feet);
+        (feet, format!("{} ft", feet))
+    };
+    ScaleBar {
+        length: feet * METERS_PER_FOOT / meters_per_pixel,
+        label,
+    }
+}
+
+// Metric and imperial scale bars, one above the other, on a box with its
+// bottom left corner at `bottom_left`. `meters_per_pixel` is the ground
+// resolution at the center of the view.
+pub fn draw_scale_bars(scene: &mut Scene, meters_per_pixel: f64, bottom_left: Point, pixel: f64) {
+    let max_length = SCALE_BAR_LENGTH * pixel;
+    let bars = [
+        metric_scale_bar(meters_per_pixel, max_length),
+        imperial_scale_bar(meters_per_pixel, max_length),
+    ];
+    let padding = 2.0 * pixel;
+    // The label, a gap and the bar with ticks two font pixels high
+    let row_height = text_height(pixel) + 3.0 * pixel;
+    let row_gap = 2.0 * pixel;
+    let content_width = bars
+        .iter()
+        .map(|bar| bar.length.max(text_width(&bar.label, pixel)))
+        .fold(0.0, f64::max);
+    let background = Rect::new(
+        bottom_left.x,
+        bottom_left.y - 2.0 * row_height - row_gap - 2.0 * padding,
+        bottom_left.x + content_width + 2.0 * padding,
+        bottom_left.y,
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        OVERLAY_BACKGROUND,
+        None,
+        &background,
+    );
+
+    let mut path = BezPath::new();
+    let left = background.x0 + padding;
+    for (i, bar) in bars.iter().enumerate() {
+        let top = background.y0 + padding + i as f64 * (row_height + row_gap);
+        path.extend(text_path(&bar.label, Point::new(left, top), pixel));
+        let bottom = top + row_height;
+        let right = left + bar.length;
+        for rect in [
+            Rect::new(left, bottom - pixel, right, bottom),
+            Rect::new(left, bottom - 2.0 * pixel, left + pixel, bottom),
+            Rect::new(right - pixel, bottom - 2.0 * pixel, right, bottom),
+        ] {
+            path.extend(rect.path_elements(0.1));
+        }
+    }
+    scene.fill(Fill::NonZero, Affine::IDENTITY, OVERLAY_TEXT, None, &path);
+}
+
+// 1, 2 or 5 times a power of ten, at most `value`
+fn round_down(value: f64) -> f64 {
+    let power = 10_f64.powf(value.log10().floor());
+    let leading = value / power;
+    let nice = if leading >= 5.0 {
+        5.0
+    } else if leading >= 2.0 {
+        2.0
+    } else {
+        1.0
+    };
+    nice * power
+}
+
+// A north arrow in the top right corner of the window, clicking it turns the
+// map back to north up
+pub struct Compass {
+    center: Point,
+    // Size of a font pixel in screen pixels
+    pixel: f64,
+}
+
+impl Compass {
+    // For a window `width` screen pixels wide
+    pub fn new(width: f64, pixel: f64) -> Self {
+        let offset = (COMPASS_MARGIN + COMPASS_RADIUS) * pixel;
+        Self {
+            center: Point::new(width - offset, offset),
+            pixel,
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (point - self.center).hypot() <= COMPASS_RADIUS * self.pixel
+    }
+
+    // The needle points north on a map turned by `bearing` degrees
+    pub fn draw(&self, scene: &mut Scene, bearing: f64) {
+        let circle = Circle::new(self.center, COMPASS_RADIUS * self.pixel);
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            OVERLAY_BACKGROUND,
+            None,
+            &circle,
+        );
+
+        let rotation = Affine::rotate_about(-bearing.to_radians(), self.center);
+        let Point { x, y } = self.center;
+        let length = 6.0 * self.pixel;
+        let half_width = 2.0 * self.pixel;
+        let needle = |tip_y: f64| {
+            let mut path = BezPath::new();
+            path.move_to((x, tip_y));
+            path.line_to((x - half_width, y));
+            path.line_to((x + half_width, y));
+            path.close_path();
+            path
+        };
+        scene.fill(
+            Fill::NonZero,
+            rotation,
+            COMPASS_NORTH,
+            None,
+            &needle(y - length),
+        );
+        scene.fill(
+            Fill::NonZero,
+            rotation,
+            OVERLAY_TEXT,
+            None,
+            &needle(y + length),
+        );
+        let label = Point::new(
+            x - text_width("N", self.pixel) / 2.0,
+            y - length - self.pixel - text_height(self.pixel),
+        );
+        scene.fill(
+            Fill::NonZero,
+            rotation,
+            OVERLAY_TEXT,
+            None,
+            &text_path("N", label, self.pixel),
+        );
+    }
+}
+
+// "1,234,567"
+pub(crate) fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut res = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            res.push(',');
+        }
+        res.push(c);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projection::{TILE_SIZE, lon_lat_to_world};
+
+    #[test]
+    fn test_status_text() {
+        let status = Status {
+            cursor: lon_lat_to_world(Point::new(2.35, -48.85)),
+            zoom: 10.25,
+            tile_zoom: 10,
+            scale: TILE_SIZE * 1024.0,
+            scale_factor: 1.0,
+        };
+        assert_eq!(
+            status.text(),
+            "LON 2.35000° E  LAT 48.85000° S  ZOOM 10.25  TILE 10/518/671  SCALE 1:179,636"
+        );
+
+        let outside = Status {
+            cursor: Point::new(-0.5, 0.5),
+            ..status
+        };
+        assert_eq!(outside.text(), "LON -  LAT -  ZOOM 10.25  TILE -  SCALE -");
+        let south = Status {
+            cursor: Point::new(0.5, 1.5),
+            ..status
+        };
+        assert!(south.text().starts_with("LON -  LAT -"));
+    }
+
+    #[test]
+    fn test_scale_bars() {
+        assert_eq!(round_down(1.0), 1.0);
+        assert_eq!(round_down(4.99), 2.0);
+        assert_eq!(round_down(730.0), 500.0);
+        assert_eq!(round_down(0.03), 0.02);
+
+        // 10 m per pixel over at most 120 pixels
+        let metric = metric_scale_bar(10.0, 120.0);
+        assert_eq!(metric.label, "1 km");
+        assert!((metric.length - 100.0).abs() < 1e-9);
+        let imperial = imperial_scale_bar(10.0, 120.0);
+        assert_eq!(imperial.label, "2000 ft");
+        assert!((imperial.length - 60.96).abs() < 1e-9);
+
+        let metric = metric_scale_bar(0.25, 120.0);
+        assert_eq!(metric.label, "20 m");
+        assert!((metric.length - 80.0).abs() < 1e-9);
+        let imperial = imperial_scale_bar(1000.0, 120.0);
+        assert_eq!(imperial.label, "50 mi");
+        assert!(imperial.length <= 120.0);
+    }
+
+    #[test]
+    fn test_compass() {
+        let compass = Compass::new(800.0, 2.0);
+        assert!(compass.contains(Point::new(800.0 - 40.0, 40.0)));
+        assert!(compass.contains(Point::new(800.0 - 10.0, 40.0)));
+        assert!(!compass.contains(Point::new(800.0 - 40.0, 75.0)));
+        assert!(!compass.contains(Point::new(400.0, 400.0)));
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(1000), "1,000");
+        assert_eq!(group_thousands(12_345_678), "12,345,678");
+    }
+}
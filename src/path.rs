@@ -1,13 +1,21 @@
 use vello::{
     Scene,
-    kurbo::{Affine, BezPath, Stroke},
+    kurbo::{Affine, BezPath, Point, Rect, Shape, Stroke},
     peniko::{self, Color},
 };
 
-use crate::layer_wrapper::LayerType;
+use crate::{
+    clip::clip_path,
+    geometry::{Coord, TypedGeometry},
+    layer_wrapper::LayerType,
+};
+
+// In screen pixels, regardless of zoom
+pub const STROKE_WIDTH: f64 = 6.0;
 
 pub struct Path {
     pub bez_path: BezPath,
+    bbox: Rect,
     color: Color,
     path_type: PathType,
     layer_type: LayerType,
@@ -41,6 +49,7 @@ impl Path {
         layer_type: LayerType,
     ) -> Self {
         Self {
+            bbox: bez_path.bounding_box(),
             bez_path,
             color,
             path_type,
@@ -48,27 +57,147 @@ impl Path {
         }
     }
 
-    pub fn draw(&self, scene: &mut Scene) {
+    pub fn bounding_box(&self) -> Rect {
+        self.bbox
+    }
+
+    // The part of this path inside `rect` after applying `transform`, None if
+    // nothing is left
+    pub fn transformed_and_clipped(&self, transform: Affine, rect: Rect) -> Option<Path> {
+        let mut bez_path = self.bez_path.clone();
+        bez_path.apply_affine(transform);
+        let clipped = clip_path(&bez_path, rect, self.is_closed());
+        if clipped.elements().is_empty() {
+            return None;
+        }
+        Some(Path::new(
+            clipped,
+            self.color,
+            self.path_type,
+            self.layer_type,
+        ))
+    }
+
+    pub fn is_closed(&self) -> bool {
+        matches!(self.path_type, PathType::Fill)
+    }
+
+    // `scale` is the number of screen pixels per tile width the path is drawn for
+    pub fn draw(&self, scene: &mut Scene, scale: f64) {
+        self.draw_as(scene, scale, &self.bez_path);
+    }
+
+    // Draws `bez_path` in place of this path, like a simplified version of it
+    pub fn draw_as(&self, scene: &mut Scene, scale: f64, bez_path: &BezPath) {
         match self.path_type {
             PathType::StrokeLine => scene.stroke(
-                &Stroke::new(6.0),
+                &Stroke::new(STROKE_WIDTH / scale),
                 Affine::IDENTITY,
                 self.color,
                 None,
-                &self.bez_path,
+                bez_path,
             ),
             PathType::Fill => scene.fill(
                 peniko::Fill::NonZero,
                 Affine::IDENTITY,
                 self.color,
                 None,
-                &self.bez_path,
+                bez_path,
             ),
         }
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum PathType {
     StrokeLine,
     Fill,
 }
+
+// Coordinates are divided by the layer's extent, so the tile spans 0..1.
+// Exterior rings wind clockwise and holes counter-clockwise, so polygons are
+// filled with the non-zero rule. Points have no path.
+pub fn create_path(geometry: &TypedGeometry, extent: u32) -> Option<(PathType, BezPath)> {
+    let scale = 1.0 / extent as f64;
+    let to_point = |c: &Coord| Point::new(c.x as f64 * scale, c.y as f64 * scale);
+    let mut path = BezPath::new();
+    let mut add_points = |coords: &[Coord], close: bool| {
+        let Some((first, rest)) = coords.split_first() else {
+            return;
+        };
+        path.move_to(to_point(first));
+        for c in rest {
+            path.line_to(to_point(c));
+        }
+        if close {
+            path.close_path();
+        }
+    };
+
+    let (path_type, polygons) = match geometry {
+        TypedGeometry::Point(_) | TypedGeometry::MultiPoint(_) => return None,
+        TypedGeometry::LineString(line) => {
+            add_points(line, false);
+            (PathType::StrokeLine, &[][..])
+        }
+        TypedGeometry::MultiLineString(lines) => {
+            for line in lines {
+                add_points(line, false);
+            }
+            (PathType::StrokeLine, &[][..])
+        }
+        TypedGeometry::Polygon(polygon) => (PathType::Fill, std::slice::from_ref(polygon)),
+        TypedGeometry::MultiPolygon(multi_polygon) => (PathType::Fill, &multi_polygon.polygons[..]),
+    };
+    for polygon in polygons {
+        add_points(&polygon.exterior, true);
+        for interior in &polygon.interiors {
+            add_points(interior, true);
+        }
+    }
+
+    Some((path_type, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encoder::encode_geometry,
+        geometry::{MultiPolygon, Polygon, commands},
+    };
+
+    #[test]
+    fn test_create_path() {
+        let coords = |points: &[(i32, i32)]| -> Vec<Coord> {
+            points.iter().map(|&(x, y)| Coord { x, y }).collect()
+        };
+        let line = TypedGeometry::LineString(coords(&[(0, 0), (5, 5)]));
+        let (path_type, line) = create_path(&line, 10).unwrap();
+        assert!(matches!(path_type, PathType::StrokeLine));
+        assert_eq!(line.to_svg(), "M0,0 L0.5,0.5");
+
+        // A square with a hole, then a zero area ring dropped when decoding
+        let polygons = vec![
+            Polygon {
+                exterior: coords(&[(0, 0), (10, 0), (10, 10), (0, 10)]),
+                interiors: vec![coords(&[(2, 2), (2, 8), (8, 8), (8, 2)])],
+            },
+            Polygon {
+                exterior: coords(&[(0, 0), (5, 0), (10, 0)]),
+                interiors: vec![],
+            },
+        ];
+        let (geom_type, encoded) =
+            encode_geometry(&TypedGeometry::MultiPolygon(MultiPolygon { polygons }));
+        let (polygon, _) = TypedGeometry::decode(commands(&encoded), geom_type).unwrap();
+        let (path_type, polygon) = create_path(&polygon, 10).unwrap();
+        assert!(matches!(path_type, PathType::Fill));
+        assert_eq!(
+            polygon.to_svg(),
+            "M0,0 L1,0 L1,1 L0,1 Z M0.2,0.2 L0.2,0.8 L0.8,0.8 L0.8,0.2 Z"
+        );
+
+        assert!(create_path(&TypedGeometry::Point(Coord { x: 1, y: 1 }), 10).is_none());
+    }
+}
@@ -0,0 +1,287 @@
+use std::f64::consts::PI;
+
+use vello::kurbo::{Point, Rect};
+
+use crate::tile_id::TileId;
+
+// Conversions between the coordinates a map deals with:
+// - lon/lat: WGS84 degrees, x is the longitude
+// - mercator: Web Mercator (EPSG:3857) meters, y pointing north
+// - world: Web Mercator scaled to the unit square, y pointing south, as drawn
+// - pixels: world coordinates at a zoom level, TILE_SIZE per tile
+// - extent: coordinates within tile z/x/y, 0..extent like in the tile data
+
+// Web Mercator is cut off here so that the world is square
+pub const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+// Of the WGS84 ellipsoid, which Web Mercator takes as a sphere
+pub const EARTH_RADIUS: f64 = 6_378_137.0;
+
+// Width of the world in Web Mercator meters
+pub const MERCATOR_WIDTH: f64 = 2.0 * PI * EARTH_RADIUS;
+
+// Width of a tile in pixels at its own zoom level
+pub const TILE_SIZE: f64 = 512.0;
+
+// Latitudes beyond MAX_LATITUDE are clamped
+pub fn lon_lat_to_world(lon_lat: Point) -> Point {
+    let lat = lon_lat.y.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    Point::new(
+        lon_lat.x / 360.0 + 0.5,
+        0.5 - (PI / 4.0 + lat / 2.0).tan().ln() / (2.0 * PI),
+    )
+}
+
+pub fn world_to_lon_lat(world: Point) -> Point {
+    let lat = (PI * (1.0 - 2.0 * world.y)).sinh().atan();
+    Point::new((world.x - 0.5) * 360.0, lat.to_degrees())
+}
+
+pub fn mercator_to_world(mercator: Point) -> Point {
+    Point::new(
+        mercator.x / MERCATOR_WIDTH + 0.5,
+        0.5 - mercator.y / MERCATOR_WIDTH,
+    )
+}
+
+pub fn world_to_mercator(world: Point) -> Point {
+    Point::new(
+        (world.x - 0.5) * MERCATOR_WIDTH,
+        (0.5 - world.y) * MERCATOR_WIDTH,
+    )
+}
+
+pub fn lon_lat_to_mercator(lon_lat: Point) -> Point {
+    world_to_mercator(lon_lat_to_world(lon_lat))
+}
+
+pub fn mercator_to_lon_lat(mercator: Point) -> Point {
+    world_to_lon_lat(mercator_to_world(mercator))
+}
+
+// `zoom` may be fractional
+pub fn world_to_pixels(world: Point, zoom: f64) -> Point {
+    (world.to_vec2() * world_size(zoom)).to_point()
+}
+
+pub fn pixels_to_world(pixels: Point, zoom: f64) -> Point {
+    (pixels.to_vec2() / world_size(zoom)).to_point()
+}
+
+// Width of the world in pixels at `zoom`
+pub fn world_size(zoom: f64) -> f64 {
+    TILE_SIZE * zoom.exp2()
+}
+
+// Meters on the ground per pixel at latitude `lat` (degrees), when the world
+// is `world_size` pixels wide
+pub fn ground_resolution(lat: f64, world_size: f64) -> f64 {
+    lat.to_radians().cos() * MERCATOR_WIDTH / world_size
+}
+
+pub fn world_to_extent(world: Point, id: TileId, extent: u32) -> Point {
+    let tile = id.transform().inverse() * world;
+    (tile.to_vec2() * extent as f64).to_point()
+}
+
+pub fn extent_to_world(point: Point, id: TileId, extent: u32) -> Point {
+    id.transform() * (point.to_vec2() / extent as f64).to_point()
+}
+
+pub fn lon_lat_to_extent(lon_lat: Point, id: TileId, extent: u32) -> Point {
+    world_to_extent(lon_lat_to_world(lon_lat), id, extent)
+}
+
+pub fn extent_to_lon_lat(point: Point, id: TileId, extent: u32) -> Point {
+    world_to_lon_lat(extent_to_world(point, id, extent))
+}
+
+// The tile of zoom level `z` containing a point, points outside of the world
+// get the nearest tile
+pub fn tile_for_world(world: Point, z: u8) -> TileId {
+    let count = 1_u64 << z;
+    let to_tile = |v: f64| (v * count as f64).floor().clamp(0.0, (count - 1) as f64) as u32;
+    TileId::new(z, to_tile(world.x), to_tile(world.y))
+}
+
+pub fn tile_for_lon_lat(lon_lat: Point, z: u8) -> TileId {
+    tile_for_world(lon_lat_to_world(lon_lat), z)
+}
+
+// West, south, east and north edges of a tile in degrees, as x0, y0, x1, y1
+pub fn tile_lon_lat_bounds(id: TileId) -> Rect {
+    let bounds = id.bounds();
+    let south_west = world_to_lon_lat(Point::new(bounds.x0, bounds.y1));
+    let north_east = world_to_lon_lat(Point::new(bounds.x1, bounds.y0));
+    Rect::from_points(south_west, north_east)
+}
+
+// Same in Web Mercator meters
+pub fn tile_mercator_bounds(id: TileId) -> Rect {
+    let bounds = id.bounds();
+    Rect::from_points(
+        world_to_mercator(Point::new(bounds.x0, bounds.y1)),
+        world_to_mercator(Point::new(bounds.x1, bounds.y0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Point, b: Point, tolerance: f64) -> bool {
+        (a - b).hypot() < tolerance
+    }
+
+    const PLACES: [(f64, f64); 5] = [
+        (0.0, 0.0),
+        (2.35, 48.85),
+        (-122.4, 37.8),
+        (179.0, -80.0),
+        (-179.9, 84.9),
+    ];
+
+    #[test]
+    fn test_lon_lat_to_world() {
+        assert!(close(
+            lon_lat_to_world(Point::ZERO),
+            Point::new(0.5, 0.5),
+            1e-12
+        ));
+        assert!(close(
+            lon_lat_to_world(Point::new(-180.0, MAX_LATITUDE)),
+            Point::ZERO,
+            1e-12
+        ));
+        assert!(close(
+            lon_lat_to_world(Point::new(180.0, -90.0)),
+            Point::new(1.0, 1.0),
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn test_world_to_lon_lat() {
+        for (lon, lat) in PLACES {
+            let lon_lat = world_to_lon_lat(lon_lat_to_world(Point::new(lon, lat)));
+            assert!(close(lon_lat, Point::new(lon, lat), 1e-9));
+        }
+        assert!((world_to_lon_lat(Point::ZERO).y - MAX_LATITUDE).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mercator() {
+        let half = MERCATOR_WIDTH / 2.0;
+        assert!(close(
+            lon_lat_to_mercator(Point::new(-180.0, MAX_LATITUDE)),
+            Point::new(-half, half),
+            1e-6
+        ));
+        // Paris, as given by EPSG:3857 tools
+        let paris = lon_lat_to_mercator(Point::new(2.35, 48.85));
+        assert!(close(paris, Point::new(261_600.8, 6_249_447.8), 1.0));
+        for (lon, lat) in PLACES {
+            let lon_lat = Point::new(lon, lat);
+            assert!(close(
+                mercator_to_lon_lat(lon_lat_to_mercator(lon_lat)),
+                lon_lat,
+                1e-9
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pixels() {
+        let world = Point::new(0.25, 0.75);
+        assert_eq!(world_to_pixels(world, 0.0), Point::new(128.0, 384.0));
+        assert_eq!(world_to_pixels(world, 2.0), Point::new(512.0, 1536.0));
+        for zoom in [0.0, 3.5, 14.0, 22.0] {
+            assert!(close(
+                pixels_to_world(world_to_pixels(world, zoom), zoom),
+                world,
+                1e-15
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ground_resolution() {
+        // The usual 156 km per pixel of a 256 pixel world, halved at 60°
+        let equator = ground_resolution(0.0, 256.0);
+        assert!((equator - 156_543.03).abs() < 0.01);
+        assert!((ground_resolution(60.0, 256.0) - equator / 2.0).abs() < 1e-6);
+        assert!((ground_resolution(0.0, world_size(1.0)) * 1024.0 - MERCATOR_WIDTH).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_extent() {
+        let id = TileId::new(1, 1, 0);
+        assert_eq!(
+            world_to_extent(Point::new(0.75, 0.25), id, 4096),
+            Point::new(2048.0, 2048.0)
+        );
+        assert_eq!(
+            extent_to_world(Point::new(0.0, 4096.0), id, 4096),
+            Point::new(0.5, 0.5)
+        );
+        // Extent coordinates may lie in the buffer outside of the tile
+        assert_eq!(
+            extent_to_world(Point::new(-4096.0, 0.0), id, 4096),
+            Point::ZERO
+        );
+
+        let id = TileId::new(14, 8528, 5976);
+        let point = lon_lat_to_extent(Point::new(7.4, 43.7), id, 4096);
+        assert!((0.0..4096.0).contains(&point.x) && (0.0..4096.0).contains(&point.y));
+        // Also from a neighbouring tile
+        for (lon, lat) in [(7.4, 43.7), (7.41, 43.71)] {
+            let lon_lat = Point::new(lon, lat);
+            let point = lon_lat_to_extent(lon_lat, id, 4096);
+            assert!(close(extent_to_lon_lat(point, id, 4096), lon_lat, 1e-9));
+        }
+    }
+
+    #[test]
+    fn test_tiles() {
+        assert_eq!(tile_for_lon_lat(Point::ZERO, 0), TileId::new(0, 0, 0));
+        assert_eq!(
+            tile_for_lon_lat(Point::new(2.35, 48.85), 1),
+            TileId::new(1, 1, 0)
+        );
+        assert_eq!(
+            tile_for_lon_lat(Point::new(2.35, 48.85), 10),
+            TileId::new(10, 518, 352)
+        );
+        // The edges of the world belong to the tiles along them
+        assert_eq!(
+            tile_for_lon_lat(Point::new(180.0, -90.0), 2),
+            TileId::new(2, 3, 3)
+        );
+        assert_eq!(
+            tile_for_world(Point::new(-1.0, 2.0), 2),
+            TileId::new(2, 0, 3)
+        );
+
+        let bounds = tile_lon_lat_bounds(TileId::new(1, 1, 0));
+        assert!(close(bounds.origin(), Point::ZERO, 1e-9));
+        assert!(close(
+            Point::new(bounds.x1, bounds.y1),
+            Point::new(180.0, MAX_LATITUDE),
+            1e-9
+        ));
+        let bounds = tile_mercator_bounds(TileId::new(1, 0, 1));
+        let half = MERCATOR_WIDTH / 2.0;
+        assert!(close(bounds.origin(), Point::new(-half, -half), 1e-6));
+        assert!(close(Point::new(bounds.x1, bounds.y1), Point::ZERO, 1e-6));
+
+        // Every point of a tile maps back to it
+        for (lon, lat) in PLACES {
+            for z in [0, 5, 12, 20] {
+                let id = tile_for_lon_lat(Point::new(lon, lat), z);
+                let bounds = tile_lon_lat_bounds(id);
+                assert!(bounds.x0 <= lon && lon <= bounds.x1);
+                assert!(bounds.y0 <= lat && lat <= bounds.y1);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::hash::{Hash, Hasher};
+
+use crate::tile::Value;
+
+// Resolves a feature's tags against its layer's keys and values, see section
+// 4.4 of the specification
+pub fn properties(
+    keys: &[String],
+    values: &[Value],
+    tags: &[u32],
+) -> Result<Vec<(String, PropertyValue)>, String> {
+    if tags.len() % 2 != 0 {
+        return Err("odd number of tags".to_owned());
+    }
+    tags.chunks(2)
+        .map(|pair| {
+            let key = keys
+                .get(pair[0] as usize)
+                .ok_or(format!("key index {} out of range", pair[0]))?;
+            let value = values
+                .get(pair[1] as usize)
+                .ok_or(format!("value index {} out of range", pair[1]))?;
+            Ok((key.clone(), PropertyValue::try_from(value)?))
+        })
+        .collect()
+}
+
+// One of the variants of Tile.Value, see section 4.1 of the specification
+#[derive(Debug, Clone)]
+pub enum PropertyValue {
+    String(String),
+    Float(f32),
+    Double(f64),
+    Int(i64),
+    UInt(u64),
+    SInt(i64),
+    Bool(bool),
+}
+
+// Floats compare by bits so that values can be deduplicated in a HashMap
+impl PartialEq for PropertyValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::Double(a), Self::Double(b)) => a.to_bits() == b.to_bits(),
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::UInt(a), Self::UInt(b)) => a == b,
+            (Self::SInt(a), Self::SInt(b)) => a == b,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PropertyValue {}
+
+impl Hash for PropertyValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::String(v) => v.hash(state),
+            Self::Float(v) => v.to_bits().hash(state),
+            Self::Double(v) => v.to_bits().hash(state),
+            Self::Int(v) | Self::SInt(v) => v.hash(state),
+            Self::UInt(v) => v.hash(state),
+            Self::Bool(v) => v.hash(state),
+        }
+    }
+}
+
+impl TryFrom<&Value> for PropertyValue {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let res = match value {
+            Value {
+                string_value: Some(v),
+                ..
+            } => Self::String(v.clone()),
+            Value {
+                float_value: Some(v),
+                ..
+            } => Self::Float(*v),
+            Value {
+                double_value: Some(v),
+                ..
+            } => Self::Double(*v),
+            Value {
+                int_value: Some(v), ..
+            } => Self::Int(*v),
+            Value {
+                uint_value: Some(v),
+                ..
+            } => Self::UInt(*v),
+            Value {
+                sint_value: Some(v),
+                ..
+            } => Self::SInt(*v),
+            Value {
+                bool_value: Some(v),
+                ..
+            } => Self::Bool(*v),
+            _ => return Err("value without any variant".to_owned()),
+        };
+        Ok(res)
+    }
+}
+
+impl From<&PropertyValue> for Value {
+    fn from(value: &PropertyValue) -> Self {
+        let mut res = Value::default();
+        match value {
+            PropertyValue::String(v) => res.string_value = Some(v.clone()),
+            PropertyValue::Float(v) => res.float_value = Some(*v),
+            PropertyValue::Double(v) => res.double_value = Some(*v),
+            PropertyValue::Int(v) => res.int_value = Some(*v),
+            PropertyValue::UInt(v) => res.uint_value = Some(*v),
+            PropertyValue::SInt(v) => res.sint_value = Some(*v),
+            PropertyValue::Bool(v) => res.bool_value = Some(*v),
+        }
+        res
+    }
+}
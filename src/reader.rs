@@ -0,0 +1,347 @@
+use prost::Message;
+
+use crate::tile::{GeomType, Value};
+
+// Field numbers of vector_tile.proto
+const TILE_LAYERS: u32 = 3;
+const LAYER_NAME: u32 = 1;
+const LAYER_FEATURES: u32 = 2;
+const LAYER_KEYS: u32 = 3;
+const LAYER_VALUES: u32 = 4;
+const LAYER_EXTENT: u32 = 5;
+const LAYER_VERSION: u32 = 15;
+const FEATURE_ID: u32 = 1;
+const FEATURE_TAGS: u32 = 2;
+const FEATURE_TYPE: u32 = 3;
+const FEATURE_GEOMETRY: u32 = 4;
+
+// Defaults of the optional fields, see vector_tile.proto
+const DEFAULT_VERSION: u32 = 1;
+const DEFAULT_EXTENT: u32 = 4096;
+
+// Reads a tile straight from its protobuf encoding without copying it:
+// layers are found by skipping over their bytes, and only the layers that
+// are asked for get their features decoded
+pub struct TileReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> TileReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = Result<LayerReader<'a>, String>> {
+        Fields::new(self.data).filter_map(|field| match field {
+            Ok((TILE_LAYERS, Field::Bytes(data))) => Some(LayerReader::new(data)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+// The header of a layer, its features are read when iterated over
+pub struct LayerReader<'a> {
+    data: &'a [u8],
+    name: &'a str,
+    version: u32,
+    extent: u32,
+    keys: Vec<&'a str>,
+    values: Vec<&'a [u8]>,
+}
+
+impl<'a> LayerReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, String> {
+        let mut res = Self {
+            data,
+            name: "",
+            version: DEFAULT_VERSION,
+            extent: DEFAULT_EXTENT,
+            keys: vec![],
+            values: vec![],
+        };
+        for field in Fields::new(data) {
+            match field? {
+                (LAYER_NAME, Field::Bytes(name)) => res.name = utf8(name)?,
+                (LAYER_KEYS, Field::Bytes(key)) => res.keys.push(utf8(key)?),
+                (LAYER_VALUES, Field::Bytes(value)) => res.values.push(value),
+                (LAYER_EXTENT, Field::Varint(extent)) => res.extent = extent as u32,
+                (LAYER_VERSION, Field::Varint(version)) => res.version = version as u32,
+                _ => (),
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn extent(&self) -> u32 {
+        self.extent
+    }
+
+    pub fn keys(&self) -> &[&'a str] {
+        &self.keys
+    }
+
+    pub fn values(&self) -> Result<Vec<Value>, String> {
+        self.values
+            .iter()
+            .map(|value| Value::decode(*value).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    pub fn features(&self) -> impl Iterator<Item = Result<FeatureReader<'a>, String>> {
+        Fields::new(self.data).filter_map(|field| match field {
+            Ok((LAYER_FEATURES, Field::Bytes(data))) => Some(FeatureReader::new(data)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+pub struct FeatureReader<'a> {
+    data: &'a [u8],
+    id: Option<u64>,
+    geom_type: GeomType,
+}
+
+impl<'a> FeatureReader<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, String> {
+        let mut res = Self {
+            data,
+            id: None,
+            geom_type: GeomType::Unknown,
+        };
+        for field in Fields::new(data) {
+            match field? {
+                (FEATURE_ID, Field::Varint(id)) => res.id = Some(id),
+                (FEATURE_TYPE, Field::Varint(geom_type)) => {
+                    res.geom_type = GeomType::try_from(geom_type as i32).unwrap_or_default()
+                }
+                (FEATURE_TAGS | FEATURE_GEOMETRY, Field::Fixed) => {
+                    return Err("fixed size repeated uint32 field".to_owned());
+                }
+                _ => (),
+            }
+        }
+        Ok(res)
+    }
+
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    pub fn geom_type(&self) -> GeomType {
+        self.geom_type
+    }
+
+    pub fn tags(&self) -> Repeated<'a> {
+        Repeated::new(self.data, FEATURE_TAGS)
+    }
+
+    pub fn geometry(&self) -> Repeated<'a> {
+        Repeated::new(self.data, FEATURE_GEOMETRY)
+    }
+}
+
+// The integers of a repeated uint32 field, packed or not: all occurrences of
+// the field in the message are read in order, as protobuf parsers must
+pub struct Repeated<'a> {
+    fields: Fields<'a>,
+    number: u32,
+    packed: &'a [u8],
+}
+
+impl<'a> Repeated<'a> {
+    fn new(data: &'a [u8], number: u32) -> Self {
+        Self {
+            fields: Fields::new(data),
+            number,
+            packed: &[],
+        }
+    }
+
+    fn read(&mut self) -> Option<Result<u64, String>> {
+        loop {
+            if !self.packed.is_empty() {
+                return Some(varint(&mut self.packed));
+            }
+            match self.fields.next()? {
+                Ok((number, Field::Bytes(packed))) if number == self.number => self.packed = packed,
+                Ok((number, Field::Varint(value))) if number == self.number => {
+                    return Some(Ok(value));
+                }
+                Ok((number, Field::Fixed)) if number == self.number => {
+                    return Some(Err("fixed size repeated uint32 field".to_owned()));
+                }
+                Ok(_) => (),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl Iterator for Repeated<'_> {
+    type Item = Result<u32, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read()? {
+            Ok(value) => Some(Ok(value as u32)),
+            Err(e) => {
+                self.fields = Fields::new(&[]);
+                self.packed = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+// A field value, see https://protobuf.dev/programming-guides/encoding/
+enum Field<'a> {
+    Varint(u64),
+    // None of the tile fields are fixed size, their values are skipped
+    Fixed,
+    Bytes(&'a [u8]),
+}
+
+// The (field number, value) pairs of a message, in order
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn read(&mut self) -> Result<(u32, Field<'a>), String> {
+        let key = varint(&mut self.data)?;
+        let field = match key & 7 {
+            0 => Field::Varint(varint(&mut self.data)?),
+            1 => {
+                take(&mut self.data, 8)?;
+                Field::Fixed
+            }
+            2 => {
+                let len = varint(&mut self.data)? as usize;
+                Field::Bytes(take(&mut self.data, len)?)
+            }
+            5 => {
+                take(&mut self.data, 4)?;
+                Field::Fixed
+            }
+            wire_type => return Err(format!("unsupported wire type {}", wire_type)),
+        };
+        Ok(((key >> 3) as u32, field))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u32, Field<'a>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let res = self.read();
+        if res.is_err() {
+            self.data = &[];
+        }
+        Some(res)
+    }
+}
+
+fn varint(data: &mut &[u8]) -> Result<u64, String> {
+    let mut res = 0;
+    for (i, &byte) in data.iter().enumerate().take(10) {
+        res |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return Ok(res);
+        }
+    }
+    Err("truncated varint".to_owned())
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if data.len() < len {
+        return Err("truncated field".to_owned());
+    }
+    let (res, rest) = data.split_at(len);
+    *data = rest;
+    Ok(res)
+}
+
+fn utf8(data: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(data).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tile;
+
+    #[test]
+    fn test_matches_prost() {
+        let data = include_bytes!("../tile1.mvt");
+        let tile = Tile::decode(data.as_slice()).unwrap();
+        let layers: Vec<LayerReader> = TileReader::new(data)
+            .layers()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(layers.len(), tile.layers.len());
+
+        for (reader, layer) in layers.iter().zip(tile.layers.iter()) {
+            assert_eq!(reader.name(), layer.name);
+            assert_eq!(reader.version(), layer.version);
+            assert_eq!(reader.extent(), layer.extent());
+            assert_eq!(reader.keys(), layer.keys.as_slice());
+            assert_eq!(reader.values().unwrap(), layer.values);
+
+            let features: Vec<FeatureReader> = reader.features().collect::<Result<_, _>>().unwrap();
+            assert_eq!(features.len(), layer.features.len());
+            for (reader, feature) in features.iter().zip(layer.features.iter()) {
+                assert_eq!(reader.id(), feature.id);
+                assert_eq!(reader.geom_type(), feature.r#type());
+                let tags: Vec<u32> = reader.tags().collect::<Result<_, _>>().unwrap();
+                assert_eq!(tags, feature.tags);
+                let geometry: Vec<u32> = reader.geometry().collect::<Result<_, _>>().unwrap();
+                assert_eq!(geometry, feature.geometry);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unpacked() {
+        // Tags 1, 2 unpacked then 3 packed, geometry split over two packed
+        // runs around the type
+        let data = [
+            0x10, 1, 0x10, 2, 0x12, 1, 3, 0x22, 1, 9, 0x18, 1, 0x22, 2, 2, 4,
+        ];
+        let feature = FeatureReader::new(&data).unwrap();
+        let tags: Vec<u32> = feature.tags().collect::<Result<_, _>>().unwrap();
+        assert_eq!(tags, vec![1, 2, 3]);
+        let geometry: Vec<u32> = feature.geometry().collect::<Result<_, _>>().unwrap();
+        assert_eq!(geometry, vec![9, 2, 4]);
+        assert_eq!(feature.geom_type(), GeomType::Point);
+
+        let prost = crate::tile::Feature::decode(data.as_slice()).unwrap();
+        assert_eq!(prost.tags, tags);
+        assert_eq!(prost.geometry, geometry);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = include_bytes!("../tile1.mvt");
+        let truncated = &data[..data.len() - 3];
+        let results: Vec<_> = TileReader::new(truncated).layers().collect();
+        assert!(results.last().unwrap().is_err());
+        assert!(varint(&mut [0xff_u8, 0xff].as_slice()).is_err());
+    }
+}
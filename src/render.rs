@@ -0,0 +1,115 @@
+use std::ops::AddAssign;
+
+use vello::{Scene, kurbo::Rect, peniko::Mix};
+
+use crate::{
+    camera::{Camera, zoom_bucket},
+    decoded_tile::DecodedTile,
+    tile_cache::TileCache,
+    tile_id::TileId,
+};
+
+// How many zoom levels down to look for tiles to draw in place of a missing one
+const MAX_DESCENDANT_LEVELS: u8 = 2;
+
+// What was appended to a scene and what was skipped
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Appended {
+    pub tiles: usize,
+    pub paths: usize,
+    pub culled_paths: usize,
+    // Of the appended paths, as encoded after simplification
+    pub vertices: usize,
+}
+
+impl AddAssign for Appended {
+    fn add_assign(&mut self, other: Self) {
+        self.tiles += other.tiles;
+        self.paths += other.paths;
+        self.culled_paths += other.culled_paths;
+        self.vertices += other.vertices;
+    }
+}
+
+// Appends the fragments of tile `id` that overlap `visible` with the given
+// opacity, clipped to `clip` if any. Both are in world coordinates.
+pub fn append_tile(
+    scene: &mut Scene,
+    camera: &Camera,
+    id: TileId,
+    tile: &mut DecodedTile,
+    clip: Option<Rect>,
+    alpha: f32,
+    visible: Rect,
+) -> Appended {
+    let tile_scale = camera.scale() * id.size();
+    let layer = if alpha < 1.0 {
+        Some(Mix::Normal)
+    } else if clip.is_some() {
+        Some(Mix::Clip)
+    } else {
+        None
+    };
+
+    if let Some(mix) = layer {
+        let area = clip.unwrap_or_else(|| {
+            tile.bounds()
+                .map_or(Rect::ZERO, |b| id.transform().transform_rect_bbox(b))
+        });
+        scene.push_layer(mix, alpha, camera.transform(), &area);
+    }
+    let area = clip.map_or(visible, |clip| clip.intersect(visible));
+    let area = id.transform().inverse().transform_rect_bbox(area);
+    let transform = camera.transform() * id.transform();
+    let mut res = Appended {
+        tiles: 1,
+        ..Appended::default()
+    };
+    for fragment in tile.fragments(zoom_bucket(tile_scale)) {
+        if fragment.bounds.overlaps(area) {
+            scene.append(&fragment.scene, Some(transform));
+            res.paths += fragment.paths;
+            res.vertices += fragment.vertices;
+        } else {
+            res.culled_paths += fragment.paths;
+        }
+    }
+    if layer.is_some() {
+        scene.pop_layer();
+    }
+    res
+}
+
+// Draws the nearest loaded ancestor (scaled up) or, failing that, loaded
+// descendants (scaled down) in place of tile `id`. Nothing was drawn if no
+// tiles were appended.
+pub fn append_fallback(
+    scene: &mut Scene,
+    camera: &Camera,
+    tiles: &mut TileCache,
+    id: TileId,
+    visible: Rect,
+) -> Appended {
+    for z in (0..id.z).rev() {
+        let ancestor = id.ancestor(z);
+        if let Some(tile) = tiles.get_fallback(ancestor) {
+            let clip = Some(id.bounds());
+            return append_tile(scene, camera, ancestor, tile, clip, 1.0, visible);
+        }
+    }
+
+    for dz in 1..=MAX_DESCENDANT_LEVELS {
+        let mut res = Appended::default();
+        for descendant in id.descendants(id.z + dz) {
+            if let Some(tile) = tiles.get_fallback(descendant) {
+                let clip = Some(descendant.bounds());
+                res += append_tile(scene, camera, descendant, tile, clip, 1.0, visible);
+            }
+        }
+        if res.tiles > 0 {
+            return res;
+        }
+    }
+
+    Appended::default()
+}
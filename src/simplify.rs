@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+
+use vello::kurbo::{BezPath, Line, ParamCurveNearest, Point};
+
+use crate::clip::{push_points, subpaths};
+
+// Douglas–Peucker: keeps the points farther than `tolerance` from the
+// simplified line, the first and last points are always kept. A closed ring
+// (first point repeated at the end) stays closed.
+pub fn simplify(points: &[Point], tolerance: f64) -> Vec<Point> {
+    if points.len() <= 2 || tolerance <= 0.0 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let tolerance_squared = tolerance * tolerance;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let line = Line::new(points[first], points[last]);
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance_squared(line, points[i])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance_squared {
+                keep[i] = true;
+                stack.push((first, i));
+                stack.push((i, last));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(&p, keep)| keep.then_some(p))
+        .collect()
+}
+
+// Points of a set of paths made of MoveTo/LineTo/ClosePath elements where
+// lines or rings meet or part ways, that is points without exactly two
+// distinct neighbours. simplify_paths keeps them, and simplifies the stretches
+// between them the same way whichever ring they belong to, so polygons
+// sharing an edge stay seamless. They don't depend on the tolerance, so they
+// are found once for all zoom levels.
+pub struct Junctions {
+    points: HashSet<PointKey>,
+}
+
+impl Junctions {
+    // `closed` tells which paths are polygons
+    pub fn new(paths: &[(&BezPath, bool)]) -> Self {
+        let mut neighbours: HashMap<PointKey, Neighbours> = HashMap::new();
+        for &(path, closed) in paths {
+            for points in closed_subpaths(path, closed) {
+                let n = points.len();
+                let wraps = closed && n > 2;
+                for (i, &p) in points.iter().enumerate() {
+                    let entry = neighbours.entry(key(p)).or_insert(Neighbours::Zero);
+                    if i > 0 || wraps {
+                        entry.add(key(points[(i + n - 1) % n]));
+                    }
+                    if i + 1 < n || wraps {
+                        entry.add(key(points[(i + 1) % n]));
+                    }
+                }
+            }
+        }
+        let points = neighbours
+            .into_iter()
+            .filter_map(|(p, n)| (!matches!(n, Neighbours::Two(..))).then_some(p))
+            .collect();
+        Self { points }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    fn contains(&self, p: Point) -> bool {
+        self.points.contains(&key(p))
+    }
+}
+
+// Simplifies `paths` (as given to Junctions::new) keeping their junctions.
+// Rings that collapse are dropped, with their holes.
+pub fn simplify_paths(
+    paths: &[(&BezPath, bool)],
+    junctions: &Junctions,
+    tolerance: f64,
+) -> Vec<BezPath> {
+    let fixed = |p: Point| junctions.contains(p);
+    paths
+        .iter()
+        .map(|&(path, closed)| {
+            let mut res = BezPath::new();
+            if !closed {
+                for line in subpaths(path) {
+                    push_points(&mut res, &simplify_between(&line, &fixed, tolerance));
+                }
+                return res;
+            }
+            // Holes follow their exterior ring. Exteriors wind like the first
+            // ring, as in MultiPolygon::from_rings: version 1 layers may have
+            // them all reversed.
+            let mut exterior_dropped = false;
+            let mut exterior_winding = None;
+            for ring in closed_subpaths(path, closed) {
+                let area = signed_area(&ring);
+                if area != 0.0 {
+                    exterior_winding.get_or_insert(area > 0.0);
+                }
+                let exterior = area != 0.0 && exterior_winding == Some(area > 0.0);
+                if !exterior && exterior_dropped {
+                    continue;
+                }
+                let simplified = simplify_ring(&ring, &fixed, tolerance);
+                if exterior {
+                    exterior_dropped = simplified.len() < 3;
+                }
+                if simplified.len() >= 3 {
+                    push_points(&mut res, &simplified);
+                    res.close_path();
+                }
+            }
+            res
+        })
+        .collect()
+}
+
+// Subpaths of `path`, without the repeated first point of closed rings
+fn closed_subpaths(path: &BezPath, closed: bool) -> Vec<Vec<Point>> {
+    let mut res = subpaths(path);
+    if closed {
+        for ring in res.iter_mut() {
+            if ring.len() > 1 && ring.first() == ring.last() {
+                ring.pop();
+            }
+        }
+    }
+    res
+}
+
+type PointKey = (u64, u64);
+
+#[derive(Clone, Copy)]
+enum Neighbours {
+    Zero,
+    One(PointKey),
+    Two(PointKey, PointKey),
+    More,
+}
+
+impl Neighbours {
+    fn add(&mut self, neighbour: PointKey) {
+        *self = match *self {
+            Neighbours::Zero => Neighbours::One(neighbour),
+            Neighbours::One(a) if a != neighbour => Neighbours::Two(a, neighbour),
+            Neighbours::Two(a, b) if a != neighbour && b != neighbour => Neighbours::More,
+            unchanged => unchanged,
+        }
+    }
+}
+
+fn key(p: Point) -> PointKey {
+    (p.x.to_bits(), p.y.to_bits())
+}
+
+// Starts at a fixed point, or at the smallest point if there is none, so that
+// the same ring is simplified the same way in every path
+fn simplify_ring(ring: &[Point], fixed: &impl Fn(Point) -> bool, tolerance: f64) -> Vec<Point> {
+    let start = ring
+        .iter()
+        .position(|&p| fixed(p))
+        .or_else(|| (0..ring.len()).min_by(|&a, &b| key(ring[a]).cmp(&key(ring[b]))));
+    let Some(start) = start else {
+        return vec![];
+    };
+    let closed: Vec<Point> = ring[start..]
+        .iter()
+        .chain(&ring[..=start])
+        .copied()
+        .collect();
+    let mut res = simplify_between(&closed, fixed, tolerance);
+    res.pop();
+    res
+}
+
+// Simplifies the stretches between fixed points (and the ends) separately,
+// each in a direction that doesn't depend on the path it belongs to
+fn simplify_between(
+    points: &[Point],
+    fixed: &impl Fn(Point) -> bool,
+    tolerance: f64,
+) -> Vec<Point> {
+    let mut res: Vec<Point> = Vec::with_capacity(points.len());
+    let mut start = 0;
+    for end in 1..points.len() {
+        if end < points.len() - 1 && !fixed(points[end]) {
+            continue;
+        }
+        let stretch = &points[start..=end];
+        let simplified = if key(stretch[0]) > key(stretch[stretch.len() - 1]) {
+            let reversed: Vec<Point> = stretch.iter().rev().copied().collect();
+            let mut simplified = simplify(&reversed, tolerance);
+            simplified.reverse();
+            simplified
+        } else {
+            simplify(stretch, tolerance)
+        };
+        // The first point is the last one of the previous stretch
+        let skip = if res.is_empty() { 0 } else { 1 };
+        res.extend_from_slice(&simplified[skip..]);
+        start = end;
+    }
+    if res.is_empty() {
+        res.extend_from_slice(points);
+    }
+    res
+}
+
+// Twice the area, positive for exterior rings, see geometry::signed_area
+fn signed_area(ring: &[Point]) -> f64 {
+    let Some(&last) = ring.last() else {
+        return 0.0;
+    };
+    let mut prev = last;
+    let mut res = 0.0;
+    for &p in ring {
+        res += prev.x * p.y - p.x * prev.y;
+        prev = p;
+    }
+    res
+}
+
+fn distance_squared(line: Line, point: Point) -> f64 {
+    if line.p0 == line.p1 {
+        return (point - line.p0).hypot2();
+    }
+    line.nearest(point, 1e-9).distance_sq
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify_line() {
+        let line = [
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.1),
+            Point::new(2.0, -0.1),
+            Point::new(3.0, 5.0),
+            Point::new(4.0, 6.0),
+            Point::new(5.0, 7.0),
+        ];
+        assert_eq!(
+            simplify(&line, 0.5),
+            vec![line[0], line[2], line[3], line[5]]
+        );
+        assert_eq!(simplify(&line, 10.0), vec![line[0], line[5]]);
+        assert_eq!(simplify(&line, 0.0), line.to_vec());
+    }
+
+    fn path(rings: &[&[(f64, f64)]], closed: bool) -> BezPath {
+        let mut res = BezPath::new();
+        for ring in rings {
+            let points: Vec<Point> = ring.iter().map(|&(x, y)| Point::new(x, y)).collect();
+            push_points(&mut res, &points);
+            if closed {
+                res.close_path();
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn test_simplify_shared_edge() {
+        // Two squares sharing a wiggly edge x = 10, listed in opposite
+        // directions and starting at different points
+        let left = path(
+            &[&[
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (10.1, 3.0),
+                (9.9, 6.0),
+                (10.1, 8.0),
+                (10.0, 10.0),
+                (0.0, 10.0),
+            ]],
+            true,
+        );
+        let right = path(
+            &[&[
+                (10.1, 8.0),
+                (9.9, 6.0),
+                (10.1, 3.0),
+                (10.0, 0.0),
+                (20.0, 0.0),
+                (20.0, 10.0),
+                (10.0, 10.0),
+            ]],
+            true,
+        );
+        let paths = [(&left, true), (&right, true)];
+        let simplified = simplify_paths(&paths, &Junctions::new(&paths), 0.5);
+        let points = |path: &BezPath| -> Vec<Point> { subpaths(path).concat() };
+        let left = points(&simplified[0]);
+        let right = points(&simplified[1]);
+        assert_eq!(left.len(), 4);
+        assert_eq!(right.len(), 4);
+        // Both lost the same wiggles, there is no gap or overlap
+        for p in [Point::new(10.0, 0.0), Point::new(10.0, 10.0)] {
+            assert!(left.contains(&p) && right.contains(&p));
+        }
+        assert!(
+            !left
+                .iter()
+                .chain(&right)
+                .any(|p| p.x != 10.0 && p.x > 9.0 && p.x < 11.0)
+        );
+
+        // Without anything shared, the wiggles go as well
+        let line = path(&[&[(0.0, 0.0), (5.0, 0.1), (10.0, 0.0)]], false);
+        let paths = [(&line, false)];
+        let alone = simplify_paths(&paths, &Junctions::new(&paths), 0.5);
+        assert_eq!(points(&alone[0]).len(), 2);
+    }
+
+    #[test]
+    fn test_simplify_collapsed_ring() {
+        // A tiny square with a hole, next to a big one
+        let polygons = path(
+            &[
+                &[(0.0, 0.0), (0.2, 0.0), (0.2, 0.2), (0.0, 0.2)],
+                &[(0.05, 0.05), (0.05, 0.1), (0.1, 0.1), (0.1, 0.05)],
+                &[(5.0, 5.0), (9.0, 5.0), (9.0, 9.0), (5.0, 9.0)],
+            ],
+            true,
+        );
+        let paths = [(&polygons, true)];
+        let simplified = simplify_paths(&paths, &Junctions::new(&paths), 0.5);
+        assert_eq!(
+            subpaths(&simplified[0]),
+            vec![vec![
+                Point::new(5.0, 5.0),
+                Point::new(9.0, 5.0),
+                Point::new(9.0, 9.0),
+                Point::new(5.0, 9.0),
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_simplify_flipped_rings() {
+        // Two squares, the first with a tiny hole, all winding the other way
+        let mut rings = [
+            vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)],
+            vec![(1.0, 1.0), (1.0, 1.1), (1.1, 1.1), (1.1, 1.0)],
+            vec![(5.0, 5.0), (9.0, 5.0), (9.0, 9.0), (5.0, 9.0)],
+        ];
+        for ring in rings.iter_mut() {
+            ring.reverse();
+        }
+        let rings: Vec<&[(f64, f64)]> = rings.iter().map(Vec::as_slice).collect();
+        let polygons = path(&rings, true);
+        let paths = [(&polygons, true)];
+        let simplified = simplify_paths(&paths, &Junctions::new(&paths), 0.5);
+        // The hole collapses, both exteriors stay
+        let rings = subpaths(&simplified[0]);
+        assert_eq!(rings.len(), 2);
+        assert!(rings[0].contains(&Point::new(4.0, 4.0)));
+        assert!(rings[1].contains(&Point::new(9.0, 9.0)));
+    }
+
+    #[test]
+    fn test_simplify_ring() {
+        let ring = [
+            Point::new(0.0, 0.0),
+            Point::new(5.0, 0.1),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+            Point::new(0.0, 0.0),
+        ];
+        let simplified = simplify(&ring, 1.0);
+        assert_eq!(
+            simplified,
+            vec![ring[0], ring[2], ring[3], ring[4], ring[5]]
+        );
+    }
+}
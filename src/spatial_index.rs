@@ -0,0 +1,317 @@
+use std::{cmp::Ordering, collections::BinaryHeap, ops::Range};
+
+use vello::kurbo::{Point, Rect};
+
+const NODE_SIZE: usize = 16;
+const HILBERT_MAX: f64 = ((1 << 16) - 1) as f64;
+
+// Packed Hilbert R-tree (static, built once): items are sorted by the Hilbert
+// value of their box centers and packed bottom-up into nodes of NODE_SIZE.
+pub struct SpatialIndex {
+    num_items: usize,
+    boxes: Vec<Rect>,
+    // For leaves: index of the original item; for nodes: position of the first child
+    indices: Vec<usize>,
+    // End position (exclusive) of every level in `boxes`, leaves first
+    level_bounds: Vec<usize>,
+}
+
+impl SpatialIndex {
+    pub fn new(items: &[Rect]) -> Self {
+        let num_items = items.len();
+        let mut boxes = Vec::with_capacity(num_items * 2);
+        let mut indices = Vec::with_capacity(num_items * 2);
+        let mut level_bounds = vec![];
+
+        if let Some(first) = items.first() {
+            let extent = items.iter().fold(*first, |acc, r| acc.union(*r));
+
+            let mut order: Vec<(u32, usize)> = items
+                .iter()
+                .enumerate()
+                .map(|(i, r)| {
+                    let center = r.center();
+                    let hx = scale_to_hilbert(center.x - extent.x0, extent.width());
+                    let hy = scale_to_hilbert(center.y - extent.y0, extent.height());
+                    (hilbert(hx, hy), i)
+                })
+                .collect();
+            order.sort_unstable();
+
+            for (_, i) in order {
+                boxes.push(items[i]);
+                indices.push(i);
+            }
+            level_bounds.push(num_items);
+
+            let mut start = 0;
+            let mut end = num_items;
+            while end - start > 1 {
+                let mut pos = start;
+                while pos < end {
+                    let children_end = (pos + NODE_SIZE).min(end);
+                    let node_box = boxes[pos..children_end]
+                        .iter()
+                        .fold(boxes[pos], |acc, r| acc.union(*r));
+                    boxes.push(node_box);
+                    indices.push(pos);
+                    pos = children_end;
+                }
+                start = end;
+                end = boxes.len();
+                level_bounds.push(end);
+            }
+        }
+
+        Self {
+            num_items,
+            boxes,
+            indices,
+            level_bounds,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.num_items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_items == 0
+    }
+
+    // Box containing all items
+    pub fn bounds(&self) -> Option<Rect> {
+        self.boxes.last().copied()
+    }
+
+    // Indices of all items whose boxes intersect `area` (touching counts)
+    pub fn search(&self, area: Rect) -> Vec<usize> {
+        let mut res = vec![];
+        let Some(root) = self.boxes.len().checked_sub(1) else {
+            return res;
+        };
+
+        let mut stack = vec![root];
+        while let Some(pos) = stack.pop() {
+            if !self.boxes[pos].overlaps(area) {
+                continue;
+            }
+            if pos < self.num_items {
+                res.push(self.indices[pos]);
+            } else {
+                stack.extend(self.children(pos));
+            }
+        }
+
+        res
+    }
+
+    // Index of the item whose box is closest to `point` (0 if the point is inside)
+    pub fn nearest(&self, point: Point) -> Option<usize> {
+        let root = self.boxes.len().checked_sub(1)?;
+
+        let mut queue = BinaryHeap::from([Candidate {
+            distance: distance_squared(&self.boxes[root], point),
+            pos: root,
+        }]);
+        // Node boxes contain their children, so the first item popped is the nearest one
+        while let Some(Candidate { pos, .. }) = queue.pop() {
+            if pos < self.num_items {
+                return Some(self.indices[pos]);
+            }
+            for child in self.children(pos) {
+                queue.push(Candidate {
+                    distance: distance_squared(&self.boxes[child], point),
+                    pos: child,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn children(&self, pos: usize) -> Range<usize> {
+        let first = self.indices[pos];
+        let level_end = self
+            .level_bounds
+            .iter()
+            .find(|&&bound| bound > first)
+            .copied()
+            .unwrap_or(self.boxes.len());
+        first..(first + NODE_SIZE).min(level_end)
+    }
+}
+
+struct Candidate {
+    distance: f64,
+    pos: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed so that BinaryHeap pops the closest candidate first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+fn distance_squared(rect: &Rect, point: Point) -> f64 {
+    let dx = (rect.x0 - point.x).max(point.x - rect.x1).max(0.0);
+    let dy = (rect.y0 - point.y).max(point.y - rect.y1).max(0.0);
+    dx * dx + dy * dy
+}
+
+fn scale_to_hilbert(offset: f64, size: f64) -> u32 {
+    if size > 0.0 {
+        (HILBERT_MAX * offset / size) as u32
+    } else {
+        0
+    }
+}
+
+// Position of (x, y) on a Hilbert curve over a 2^16 x 2^16 grid, see
+// "Fast Hilbert curve generation, sorting, and range queries" by rawrunprotected
+fn hilbert(x: u32, y: u32) -> u32 {
+    let mut a = x ^ y;
+    let mut b = 0xFFFF ^ a;
+    let mut c = 0xFFFF ^ (x | y);
+    let mut d = x & (y ^ 0xFFFF);
+
+    let mut aa = a | (b >> 1);
+    let mut bb = (a >> 1) ^ a;
+    let mut cc = ((c >> 1) ^ (b & (d >> 1))) ^ c;
+    let mut dd = ((a & (c >> 1)) ^ (d >> 1)) ^ d;
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 2)) ^ (b & (b >> 2));
+    bb = (a & (b >> 2)) ^ (b & ((a ^ b) >> 2));
+    cc ^= (a & (c >> 2)) ^ (b & (d >> 2));
+    dd ^= (b & (c >> 2)) ^ ((a ^ b) & (d >> 2));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    aa = (a & (a >> 4)) ^ (b & (b >> 4));
+    bb = (a & (b >> 4)) ^ (b & ((a ^ b) >> 4));
+    cc ^= (a & (c >> 4)) ^ (b & (d >> 4));
+    dd ^= (b & (c >> 4)) ^ ((a ^ b) & (d >> 4));
+
+    a = aa;
+    b = bb;
+    c = cc;
+    d = dd;
+    cc ^= (a & (c >> 8)) ^ (b & (d >> 8));
+    dd ^= (b & (c >> 8)) ^ ((a ^ b) & (d >> 8));
+
+    a = cc ^ (cc >> 1);
+    b = dd ^ (dd >> 1);
+
+    let i0 = interleave(x ^ y);
+    let i1 = interleave(b | (0xFFFF ^ ((x ^ y) | a)));
+
+    (i1 << 1) | i0
+}
+
+fn interleave(mut v: u32) -> u32 {
+    v &= 0xFFFF;
+    v = (v | (v << 8)) & 0x00FF00FF;
+    v = (v | (v << 4)) & 0x0F0F0F0F;
+    v = (v | (v << 2)) & 0x33333333;
+    (v | (v << 1)) & 0x55555555
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic pseudo-random numbers in 0..1, no need for a rand dependency
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        fn rect(&mut self, max_size: f64) -> Rect {
+            let x = self.next() * 4096.0;
+            let y = self.next() * 4096.0;
+            Rect::new(x, y, x + self.next() * max_size, y + self.next() * max_size)
+        }
+    }
+
+    fn random_items(count: usize) -> Vec<Rect> {
+        let mut rng = Lcg(42);
+        (0..count).map(|_| rng.rect(100.0)).collect()
+    }
+
+    #[test]
+    fn test_search_matches_brute_force() {
+        let items = random_items(1000);
+        let index = SpatialIndex::new(&items);
+        assert_eq!(index.len(), 1000);
+
+        let mut rng = Lcg(7);
+        for _ in 0..100 {
+            let area = rng.rect(800.0);
+            let mut found = index.search(area);
+            found.sort();
+            let expected: Vec<usize> = items
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| r.overlaps(area))
+                .map(|(i, _)| i)
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn test_nearest_matches_brute_force() {
+        let items = random_items(1000);
+        let index = SpatialIndex::new(&items);
+
+        let mut rng = Lcg(11);
+        for _ in 0..100 {
+            let point = Point::new(rng.next() * 5000.0 - 500.0, rng.next() * 5000.0 - 500.0);
+            let found = index.nearest(point).unwrap();
+            let expected = items
+                .iter()
+                .map(|r| distance_squared(r, point))
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!(distance_squared(&items[found], point), expected);
+        }
+    }
+
+    #[test]
+    fn test_small_indexes() {
+        let empty = SpatialIndex::new(&[]);
+        assert!(empty.search(Rect::new(0.0, 0.0, 10.0, 10.0)).is_empty());
+        assert_eq!(empty.nearest(Point::ZERO), None);
+
+        let single = SpatialIndex::new(&[Rect::new(5.0, 5.0, 6.0, 6.0)]);
+        assert_eq!(single.search(Rect::new(0.0, 0.0, 10.0, 10.0)), vec![0]);
+        assert!(single.search(Rect::new(7.0, 7.0, 10.0, 10.0)).is_empty());
+        assert_eq!(single.nearest(Point::new(100.0, 100.0)), Some(0));
+    }
+}
@@ -0,0 +1,187 @@
+use vello::{
+    Scene,
+    kurbo::{Affine, BezPath, Point, Rect, Shape},
+    peniko::{Color, Fill},
+};
+
+// Glyphs are GLYPH_WIDTH x GLYPH_HEIGHT font pixels, followed by one blank column
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+const ADVANCE: usize = GLYPH_WIDTH + 1;
+
+// A bitmap font for overlays, so that no font file has to be found at run
+// time. Rows from the top, the highest of the 5 bits is the left column.
+// Lower case letters are drawn as upper case ones.
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    ('A', [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08]),
+    (':', [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00]),
+    ('=', [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('?', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('\'', [0x0c, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('"', [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00]),
+    ('°', [0x0c, 0x12, 0x12, 0x0c, 0x00, 0x00, 0x00]),
+    ('|', [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f]),
+];
+
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(g, _)| *g == c)
+        .or_else(|| GLYPHS.iter().find(|(g, _)| *g == '?'))
+        .map(|(_, rows)| *rows)
+        .unwrap()
+}
+
+// Width of `text` drawn with font pixels of `pixel` screen pixels
+pub fn text_width(text: &str, pixel: f64) -> f64 {
+    let count = text.chars().count();
+    (count * ADVANCE).saturating_sub(1) as f64 * pixel
+}
+
+pub fn text_height(pixel: f64) -> f64 {
+    GLYPH_HEIGHT as f64 * pixel
+}
+
+// Outline of `text` with its top left corner at `origin`
+pub fn text_path(text: &str, origin: Point, pixel: f64) -> BezPath {
+    let mut path = BezPath::new();
+    for (i, c) in text.chars().enumerate() {
+        let left = origin.x + (i * ADVANCE) as f64 * pixel;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let top = origin.y + row as f64 * pixel;
+            // One rectangle per run of set bits
+            let mut column = 0;
+            while column < GLYPH_WIDTH {
+                let set = |column: usize| bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0;
+                if !set(column) {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < GLYPH_WIDTH && set(column) {
+                    column += 1;
+                }
+                let rect = Rect::new(
+                    left + start as f64 * pixel,
+                    top,
+                    left + column as f64 * pixel,
+                    top + pixel,
+                );
+                path.extend(rect.path_elements(0.1));
+            }
+        }
+    }
+    path
+}
+
+pub fn draw_text(scene: &mut Scene, text: &str, origin: Point, pixel: f64, color: Color) {
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        color,
+        None,
+        &text_path(text, origin, pixel),
+    );
+}
+
+// Text on a box `padding` screen pixels larger on every side, `origin` is the
+// top left corner of the box. Returns the box.
+pub fn draw_label(
+    scene: &mut Scene,
+    text: &str,
+    origin: Point,
+    pixel: f64,
+    padding: f64,
+    colors: (Color, Color),
+) -> Rect {
+    let (foreground, background) = colors;
+    let rect = Rect::from_origin_size(
+        origin,
+        (
+            text_width(text, pixel) + 2.0 * padding,
+            text_height(pixel) + 2.0 * padding,
+        ),
+    );
+    scene.fill(Fill::NonZero, Affine::IDENTITY, background, None, &rect);
+    let text_origin = origin + (padding, padding);
+    draw_text(scene, text, text_origin, pixel, foreground);
+    rect
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_path() {
+        assert_eq!(text_width("", 2.0), 0.0);
+        assert_eq!(text_width("AB", 2.0), 22.0);
+
+        // "-" is a single run, "1" has one per row
+        let dash = text_path("-", Point::new(10.0, 20.0), 2.0);
+        assert_eq!(dash.bounding_box(), Rect::new(10.0, 26.0, 20.0, 28.0));
+        let one = text_path("1", Point::ZERO, 1.0);
+        let runs = one
+            .elements()
+            .iter()
+            .filter(|e| matches!(e, vello::kurbo::PathEl::MoveTo(_)))
+            .count();
+        assert_eq!(runs, 7);
+
+        // Lower case and unknown characters
+        assert_eq!(
+            text_path("a", Point::ZERO, 1.0),
+            text_path("A", Point::ZERO, 1.0)
+        );
+        assert_eq!(
+            text_path("€", Point::ZERO, 1.0),
+            text_path("?", Point::ZERO, 1.0)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use vello::kurbo::Point;
+
+use crate::{decoded_tile::DecodedTile, tile_id::TileId};
+
+// Among this many least recently used tiles the one farthest from the viewport
+// is evicted first
+const EVICTION_WINDOW: usize = 8;
+
+struct Entry {
+    tile: DecodedTile,
+    size: usize,
+    last_used_frame: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub tiles: usize,
+    pub bytes: usize,
+    pub budget: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
+// Decoded tiles bounded by an approximate memory budget in bytes
+pub struct TileCache {
+    entries: HashMap<TileId, Entry>,
+    budget: usize,
+    used: usize,
+    frame: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl TileCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            used: 0,
+            frame: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    // Counts a hit when the tile comes back into use, not in every frame it
+    // is used in
+    pub fn get(&mut self, id: TileId) -> Option<&mut DecodedTile> {
+        let entry = self.entries.get_mut(&id)?;
+        if entry.last_used_frame + 1 < self.frame {
+            self.hits += 1;
+        }
+        entry.last_used_frame = self.frame;
+        Some(&mut entry.tile)
+    }
+
+    // Called when a tile that wasn't found starts loading, once per load
+    // however many frames it takes
+    pub fn count_miss(&mut self) {
+        self.misses += 1;
+    }
+
+    // Like get, but not counted in the hit/miss stats: used to look for
+    // tiles that can stand in for missing ones
+    pub fn get_fallback(&mut self, id: TileId) -> Option<&mut DecodedTile> {
+        let entry = self.entries.get_mut(&id)?;
+        entry.last_used_frame = self.frame;
+        Some(&mut entry.tile)
+    }
+
+    pub fn insert(&mut self, id: TileId, tile: DecodedTile) {
+        let size = tile.approx_size();
+        self.used += size;
+        let old = self.entries.insert(
+            id,
+            Entry {
+                tile,
+                size,
+                last_used_frame: self.frame,
+            },
+        );
+        if let Some(old) = old {
+            self.used -= old.size;
+        }
+    }
+
+    // Updates sizes of the tiles used in this frame (their fragments may have
+    // been re-encoded) and evicts tiles not used in it until the cache fits
+    // the budget. `center` is the viewport center in world coordinates.
+    pub fn end_frame(&mut self, center: Point) {
+        for entry in self.entries.values_mut() {
+            if entry.last_used_frame == self.frame {
+                let size = entry.tile.approx_size();
+                self.used = self.used - entry.size + size;
+                entry.size = size;
+            }
+        }
+
+        while self.used > self.budget {
+            let mut candidates: Vec<(u64, TileId)> = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.last_used_frame < self.frame)
+                .map(|(id, entry)| (entry.last_used_frame, *id))
+                .collect();
+            candidates.sort_unstable_by_key(|(last_used_frame, _)| *last_used_frame);
+            let Some(victim) = candidates
+                .iter()
+                .take(EVICTION_WINDOW)
+                .map(|(_, id)| *id)
+                .max_by(|a, b| {
+                    let da = a.bounds().center().distance(center);
+                    let db = b.bounds().center().distance(center);
+                    da.total_cmp(&db)
+                })
+            else {
+                // Everything left is on screen
+                break;
+            };
+            let entry = self.entries.remove(&victim).unwrap();
+            self.used -= entry.size;
+            log::trace!("evicted tile {} ({} bytes)", victim, entry.size);
+        }
+
+        self.frame += 1;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            tiles: self.entries.len(),
+            bytes: self.used,
+            budget: self.budget,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eviction() {
+        let tile_size = DecodedTile::empty().approx_size();
+        let mut cache = TileCache::new(2 * tile_size);
+        let near = TileId::new(2, 0, 0);
+        let far = TileId::new(2, 3, 3);
+        let current = TileId::new(2, 1, 0);
+
+        cache.insert(far, DecodedTile::empty());
+        cache.insert(near, DecodedTile::empty());
+        cache.end_frame(Point::ZERO);
+        assert_eq!(cache.stats().tiles, 2);
+
+        // Tiles used in the current frame stay, the far one goes first
+        cache.insert(current, DecodedTile::empty());
+        cache.end_frame(Point::ZERO);
+        assert!(cache.get(far).is_none());
+        assert!(cache.get(near).is_some());
+        assert!(cache.get(current).is_some());
+        assert!(cache.stats().bytes <= cache.stats().budget);
+        // `near` comes back into use, `current` was only just inserted
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().misses, 0);
+    }
+
+    #[test]
+    fn test_hits_per_use() {
+        let id = TileId::new(1, 0, 0);
+        let mut cache = TileCache::new(usize::MAX);
+        cache.count_miss();
+        cache.insert(id, DecodedTile::empty());
+
+        // Used frame after frame, then again after a break
+        for _ in 0..3 {
+            assert!(cache.get(id).is_some());
+            cache.end_frame(Point::ZERO);
+        }
+        assert_eq!(cache.stats().hits, 0);
+        cache.end_frame(Point::ZERO);
+        assert!(cache.get(id).is_some());
+        assert!(cache.get(id).is_some());
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().misses, 1);
+        assert_eq!(cache.stats().hit_rate(), 0.5);
+    }
+}
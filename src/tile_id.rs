@@ -0,0 +1,102 @@
+use std::{fmt, str::FromStr};
+
+use vello::kurbo::{Affine, Rect};
+
+// Tile coordinates are normalized by layer extents, so every tile spans the
+// unit square
+pub const TILE_BOUNDS: Rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TileId {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileId {
+    pub fn new(z: u8, x: u32, y: u32) -> Self {
+        Self { z, x, y }
+    }
+
+    // The tile of zoom level `z` (not above this one's) containing this tile
+    pub fn ancestor(&self, z: u8) -> TileId {
+        let dz = self.z - z;
+        TileId::new(z, self.x >> dz, self.y >> dz)
+    }
+
+    // Tiles of zoom level `z` (not below this one's) covering this tile
+    pub fn descendants(&self, z: u8) -> Vec<TileId> {
+        let dz = z - self.z;
+        let count = 1 << dz;
+        let mut res = Vec::with_capacity(count * count);
+        for x in 0..count as u32 {
+            for y in 0..count as u32 {
+                res.push(TileId::new(z, (self.x << dz) + x, (self.y << dz) + y));
+            }
+        }
+        res
+    }
+
+    // Width of the tile in world coordinates, where the world is the unit square
+    pub fn size(&self) -> f64 {
+        (-(self.z as f64)).exp2()
+    }
+
+    pub fn bounds(&self) -> Rect {
+        let size = self.size();
+        Rect::new(
+            self.x as f64 * size,
+            self.y as f64 * size,
+            (self.x + 1) as f64 * size,
+            (self.y + 1) as f64 * size,
+        )
+    }
+
+    // Maps tile coordinates to world coordinates
+    pub fn transform(&self) -> Affine {
+        let size = self.size();
+        Affine::translate((self.x as f64 * size, self.y as f64 * size)) * Affine::scale(size)
+    }
+
+    // Tiles of zoom level `z` that intersect `area` (in world coordinates)
+    pub fn covering(area: Rect, z: u8) -> Vec<TileId> {
+        let count = 1_u64 << z;
+        let to_tile = |v: f64| (v * count as f64).floor().clamp(0.0, (count - 1) as f64) as u32;
+        if area.x1 < 0.0 || area.y1 < 0.0 || area.x0 > 1.0 || area.y0 > 1.0 {
+            return vec![];
+        }
+
+        let mut res = vec![];
+        for x in to_tile(area.x0)..=to_tile(area.x1) {
+            for y in to_tile(area.y0)..=to_tile(area.y1) {
+                res.push(TileId::new(z, x, y));
+            }
+        }
+        res
+    }
+}
+
+impl fmt::Display for TileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.z, self.x, self.y)
+    }
+}
+
+impl FromStr for TileId {
+    type Err = String;
+
+    // Parses "z/x/y"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('/').collect();
+        let [z, x, y] = parts.as_slice() else {
+            return Err(format!("tile id {} is not z/x/y", s));
+        };
+        let z: u8 = z.parse().map_err(|_| format!("wrong zoom in {}", s))?;
+        let x: u32 = x.parse().map_err(|_| format!("wrong x in {}", s))?;
+        let y: u32 = y.parse().map_err(|_| format!("wrong y in {}", s))?;
+        if z > 31 || x as u64 >= 1 << z || y as u64 >= 1 << z {
+            return Err(format!("tile {} is out of range", s));
+        }
+        Ok(Self::new(z, x, y))
+    }
+}
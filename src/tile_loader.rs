@@ -0,0 +1,309 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use winit::event_loop::EventLoopProxy;
+
+use crate::{
+    UserEvent,
+    decoded_tile::DecodedTile,
+    layer_wrapper::LayerWrapper,
+    reader::TileReader,
+    tile_id::TileId,
+    tile_source::{self, TileSource},
+};
+
+// Tiles above the source's max zoom are cut out of ancestors at the max zoom
+pub const MAX_OVERZOOM: u8 = 20;
+
+// How many decoded max zoom tiles are kept around for overzooming
+const PARENT_CACHE_SIZE: usize = 4;
+
+// Reads and decodes tiles on a pool of worker threads, delivering them to the
+// event loop as UserEvent::TileLoaded
+pub struct TileLoader {
+    sender: Sender<TileId>,
+    shared: Arc<Shared>,
+}
+
+// State shared between the loader and its workers
+struct Shared {
+    source: TileSource,
+    // Clip buffer for decoded tiles, see DecodedTile::new
+    buffer: f64,
+    // Names of the layers to decode, all of them if None
+    layers: Option<HashSet<String>>,
+    requests: Mutex<Requests>,
+    // Most recently used last
+    parents: Mutex<Vec<(TileId, Arc<DecodedTile>)>>,
+}
+
+// A tile is loaded by one worker at a time: requesting it again while a
+// cancelled load of it is still under way brings that load back
+#[derive(Default)]
+struct Requests {
+    // Requested and not received by the event loop yet, workers give up on
+    // tiles removed from here
+    wanted: HashSet<TileId>,
+    // Sent to the workers and not given up on or received by the event loop yet
+    in_flight: HashSet<TileId>,
+}
+
+impl Requests {
+    // Whether the tile has to be sent to the workers
+    fn request(&mut self, id: TileId) -> bool {
+        self.wanted.insert(id);
+        self.in_flight.insert(id)
+    }
+
+    // Called by a worker about to give up on a tile, whether it has to carry on
+    fn keep(&mut self, id: TileId) -> bool {
+        let keep = self.wanted.contains(&id);
+        if !keep {
+            self.in_flight.remove(&id);
+        }
+        keep
+    }
+
+    fn finish(&mut self, id: TileId) {
+        self.wanted.remove(&id);
+        self.in_flight.remove(&id);
+    }
+}
+
+impl TileLoader {
+    pub fn new(
+        source: TileSource,
+        buffer: f64,
+        layers: Option<HashSet<String>>,
+        proxy: EventLoopProxy<UserEvent>,
+        workers: usize,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let shared = Arc::new(Shared {
+            source,
+            buffer,
+            layers,
+            requests: Mutex::new(Requests::default()),
+            parents: Mutex::new(Vec::new()),
+        });
+
+        for i in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            let shared = Arc::clone(&shared);
+            let proxy = proxy.clone();
+            thread::Builder::new()
+                .name(format!("tile-loader-{}", i))
+                .spawn(move || work(&receiver, &shared, &proxy))
+                .unwrap();
+        }
+
+        Self { sender, shared }
+    }
+
+    // Returns whether a load was started, rather than one already under way
+    // reused
+    pub fn request(&self, id: TileId) -> bool {
+        let started = self.shared.requests.lock().unwrap().request(id);
+        if started {
+            log::trace!("requesting tile {}", id);
+            self.sender.send(id).unwrap();
+        }
+        started
+    }
+
+    // Cancels requests for tiles that are not wanted anymore
+    pub fn retain(&self, wanted: &[TileId]) {
+        self.shared.requests.lock().unwrap().wanted.retain(|id| {
+            let keep = wanted.contains(id);
+            if !keep {
+                log::trace!("cancelling tile {}", id);
+            }
+            keep
+        });
+    }
+
+    // Called when the event loop receives a tile
+    pub fn finish(&self, id: TileId) {
+        self.shared.requests.lock().unwrap().finish(id);
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.shared.requests.lock().unwrap().wanted.len()
+    }
+
+    // Clip buffer of the tiles it decodes, in tile widths
+    pub fn buffer(&self) -> f64 {
+        self.shared.buffer
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<TileId>>, shared: &Shared, proxy: &EventLoopProxy<UserEvent>) {
+    'requests: loop {
+        // The lock is released before loading so other workers can pick up requests
+        let Ok(id) = receiver.lock().unwrap().recv() else {
+            return;
+        };
+        let is_cancelled = || !shared.requests.lock().unwrap().wanted.contains(&id);
+
+        let (_, max_zoom) = shared.source.zoom_range();
+        let result = loop {
+            let result = if id.z > max_zoom {
+                load_overzoomed(shared, id, max_zoom, &is_cancelled)
+            } else {
+                load(shared, id, &is_cancelled)
+            };
+            match result {
+                Ok(Some(tile)) => break Ok(tile),
+                Err(e) => break Err(e),
+                Ok(None) => {
+                    if !shared.requests.lock().unwrap().keep(id) {
+                        log::trace!("tile {} cancelled", id);
+                        continue 'requests;
+                    }
+                    // Requested again since it was cancelled, load it after all
+                }
+            }
+        };
+        if !shared.requests.lock().unwrap().keep(id) {
+            log::trace!("tile {} cancelled", id);
+            continue;
+        }
+        if proxy.send_event(UserEvent::TileLoaded(id, result)).is_err() {
+            // Event loop is gone
+            return;
+        }
+    }
+}
+
+fn load_overzoomed(
+    shared: &Shared,
+    id: TileId,
+    max_zoom: u8,
+    is_cancelled: &dyn Fn() -> bool,
+) -> Result<Option<DecodedTile>, String> {
+    let parent_id = id.ancestor(max_zoom);
+
+    let cached = {
+        let mut parents = shared.parents.lock().unwrap();
+        let position = parents.iter().position(|(i, _)| *i == parent_id);
+        position.map(|position| {
+            let entry = parents.remove(position);
+            let parent = Arc::clone(&entry.1);
+            parents.push(entry);
+            parent
+        })
+    };
+    let parent = match cached {
+        Some(parent) => parent,
+        None => {
+            let Some(parent) = load(shared, parent_id, is_cancelled)? else {
+                return Ok(None);
+            };
+            let parent = Arc::new(parent);
+            let mut parents = shared.parents.lock().unwrap();
+            if parents.len() >= PARENT_CACHE_SIZE {
+                parents.remove(0);
+            }
+            parents.push((parent_id, Arc::clone(&parent)));
+            parent
+        }
+    };
+
+    if is_cancelled() {
+        return Ok(None);
+    }
+    Ok(Some(DecodedTile::overzoomed(
+        &parent,
+        parent_id,
+        id,
+        shared.buffer,
+    )))
+}
+
+// Ok(None) if the request was cancelled in the middle
+fn load(
+    shared: &Shared,
+    id: TileId,
+    is_cancelled: &dyn Fn() -> bool,
+) -> Result<Option<DecodedTile>, String> {
+    if is_cancelled() {
+        return Ok(None);
+    }
+    let Some(data) = shared.source.read(id)? else {
+        // Nothing to draw there
+        return Ok(Some(DecodedTile::empty()));
+    };
+
+    if is_cancelled() {
+        return Ok(None);
+    }
+    let data = tile_source::decompress(data)?;
+
+    if is_cancelled() {
+        return Ok(None);
+    }
+    let layers = decode_layers(&data, shared.layers.as_ref())?;
+
+    if is_cancelled() {
+        return Ok(None);
+    }
+    Ok(Some(DecodedTile::new(layers, shared.buffer)))
+}
+
+// Reads the layers named in `layers`, or all of them if None. The features
+// of other layers are skipped over without being read, and those of the
+// returned layers borrow their geometry and properties from `data`.
+pub fn decode_layers<'a>(
+    data: &'a [u8],
+    layers: Option<&HashSet<String>>,
+) -> Result<Vec<LayerWrapper<'a>>, String> {
+    let mut res = Vec::new();
+    for layer in TileReader::new(data).layers() {
+        let layer = layer?;
+        if layers.is_some_and(|layers| !layers.contains(layer.name())) {
+            continue;
+        }
+        match LayerWrapper::new(&layer) {
+            Ok(layer_wrapper) => res.push(layer_wrapper),
+            Err(e) => log::warn!("skipping layer: {}", e),
+        }
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests() {
+        let id = TileId::new(3, 1, 2);
+        let mut requests = Requests::default();
+        assert!(requests.request(id));
+        assert!(!requests.request(id));
+
+        // Cancelled and requested again while a worker is loading it: the
+        // worker carries on and the tile isn't sent a second time
+        requests.wanted.remove(&id);
+        assert!(!requests.request(id));
+        assert!(requests.keep(id));
+
+        // Until the tile is received, it isn't sent again
+        assert!(!requests.request(id));
+        requests.finish(id);
+        assert!(requests.request(id));
+
+        // Given up on, it is sent again when requested
+        requests.wanted.remove(&id);
+        assert!(!requests.keep(id));
+        assert!(requests.request(id));
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fs,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use flate2::read::GzDecoder;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+
+use crate::tile_id::TileId;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+pub enum TileSource {
+    // A single tile file, shown as tile `id`
+    File {
+        path: PathBuf,
+        id: TileId,
+    },
+    // A `{z}/{x}/{y}.mvt` (or `.pbf`) directory tree
+    Directory {
+        path: PathBuf,
+        min_zoom: u8,
+        max_zoom: u8,
+    },
+    // An MBTiles archive, rows are numbered from the bottom (TMS scheme)
+    MBTiles {
+        connection: Mutex<Connection>,
+        min_zoom: u8,
+        max_zoom: u8,
+    },
+}
+
+impl TileSource {
+    pub fn open(path: PathBuf, id: TileId) -> Result<Self, String> {
+        let metadata = fs::metadata(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if !metadata.is_dir() {
+            if path.extension().is_some_and(|e| e == "mbtiles") {
+                return Self::open_mbtiles(&path);
+            }
+            return Ok(Self::File { path, id });
+        }
+
+        let zooms: Vec<u8> = numeric_entries(&path)?
+            .into_iter()
+            .filter_map(|z| u8::try_from(z).ok())
+            .collect();
+        let (Some(&min_zoom), Some(&max_zoom)) = (zooms.iter().min(), zooms.iter().max()) else {
+            return Err(format!("{}: no zoom level directories", path.display()));
+        };
+
+        Ok(Self::Directory {
+            path,
+            min_zoom,
+            max_zoom,
+        })
+    }
+
+    fn open_mbtiles(path: &Path) -> Result<Self, String> {
+        let error = |e: rusqlite::Error| format!("{}: {}", path.display(), e);
+        let connection =
+            Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(error)?;
+        let (min_zoom, max_zoom): (Option<u8>, Option<u8>) = connection
+            .query_row(
+                "SELECT MIN(zoom_level), MAX(zoom_level) FROM tiles",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(error)?;
+        let (Some(min_zoom), Some(max_zoom)) = (min_zoom, max_zoom) else {
+            return Err(format!("{}: no tiles", path.display()));
+        };
+
+        Ok(Self::MBTiles {
+            connection: Mutex::new(connection),
+            min_zoom,
+            max_zoom,
+        })
+    }
+
+    pub fn zoom_range(&self) -> (u8, u8) {
+        match self {
+            Self::File { id, .. } => (id.z, id.z),
+            Self::Directory {
+                min_zoom, max_zoom, ..
+            }
+            | Self::MBTiles {
+                min_zoom, max_zoom, ..
+            } => (*min_zoom, *max_zoom),
+        }
+    }
+
+    // Some tile to start browsing from
+    pub fn first_tile(&self) -> Result<TileId, String> {
+        match self {
+            Self::File { id, .. } => Ok(*id),
+            Self::Directory { path, min_zoom, .. } => {
+                let z_path = path.join(min_zoom.to_string());
+                let x = *numeric_entries(&z_path)?
+                    .iter()
+                    .min()
+                    .ok_or(format!("{}: no tiles", z_path.display()))?;
+                let x_path = z_path.join(x.to_string());
+                let y = *numeric_entries(&x_path)?
+                    .iter()
+                    .min()
+                    .ok_or(format!("{}: no tiles", x_path.display()))?;
+                Ok(TileId::new(*min_zoom, x, y))
+            }
+            Self::MBTiles {
+                connection,
+                min_zoom,
+                ..
+            } => {
+                let (x, row): (u32, u32) = connection
+                    .lock()
+                    .unwrap()
+                    .query_row(
+                        "SELECT tile_column, tile_row FROM tiles WHERE zoom_level = ?1
+                         ORDER BY tile_column, tile_row DESC LIMIT 1",
+                        params![min_zoom],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .map_err(|e| e.to_string())?;
+                Ok(TileId::new(*min_zoom, x, flip_row(*min_zoom, row)))
+            }
+        }
+    }
+
+    // Raw (possibly compressed) tile data, None if the source has no such tile
+    pub fn read(&self, id: TileId) -> Result<Option<Vec<u8>>, String> {
+        let candidates = match self {
+            Self::File { path, id: file_id } => {
+                if id != *file_id {
+                    return Ok(None);
+                }
+                vec![path.clone()]
+            }
+            Self::Directory { path, .. } => ["mvt", "pbf"]
+                .iter()
+                .map(|ext| path.join(format!("{}/{}/{}.{}", id.z, id.x, id.y, ext)))
+                .collect(),
+            Self::MBTiles { connection, .. } => {
+                return connection
+                    .lock()
+                    .unwrap()
+                    .query_row(
+                        "SELECT tile_data FROM tiles
+                         WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                        params![id.z, id.x, flip_row(id.z, id.y)],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|e| format!("tile {}: {}", id, e));
+            }
+        };
+
+        for candidate in candidates {
+            match fs::read(&candidate) {
+                Ok(data) => return Ok(Some(data)),
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(format!("{}: {}", candidate.display(), e)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+// Converts between XYZ rows (from the top) and TMS rows (from the bottom)
+pub fn flip_row(z: u8, row: u32) -> u32 {
+    (1_u32 << z) - 1 - row
+}
+
+// Tiles are often stored gzipped, plain data is returned as is
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !data.starts_with(&GZIP_MAGIC) {
+        return Ok(data);
+    }
+
+    let mut res = Vec::new();
+    GzDecoder::new(data.as_slice())
+        .read_to_end(&mut res)
+        .map_err(|e| format!("gzip: {}", e))?;
+    Ok(res)
+}
+
+// Directory entries named like numbers, with extensions stripped
+fn numeric_entries(path: &Path) -> Result<Vec<u32>, String> {
+    let entries = fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            let name = name.to_str()?;
+            name.split('.').next()?.parse().ok()
+        })
+        .collect())
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use flate2::{Compression, write::GzEncoder};
+use rusqlite::{Connection, params};
+
+use crate::{tile_id::TileId, tile_source::flip_row};
+
+pub enum TileWriter {
+    // A `{z}/{x}/{y}.mvt` directory tree of uncompressed tiles
+    Directory(PathBuf),
+    // An MBTiles 1.3 archive of gzipped tiles, written to `partial` and
+    // renamed to `path` when finished so that failed runs leave no archive
+    MBTiles {
+        connection: Connection,
+        partial: PathBuf,
+        path: PathBuf,
+    },
+}
+
+impl TileWriter {
+    // Paths ending in `.mbtiles` are written as archives. Existing archives
+    // are not overwritten.
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let error = |e: String| format!("{}: {}", path.display(), e);
+        if path.extension().is_none_or(|e| e != "mbtiles") {
+            fs::create_dir_all(path).map_err(|e| error(e.to_string()))?;
+            return Ok(Self::Directory(path.to_owned()));
+        }
+
+        if path.exists() {
+            return Err(error("already exists".to_owned()));
+        }
+        // Left over by a failed run
+        let partial = path.with_extension("mbtiles.partial");
+        if partial.exists() {
+            fs::remove_file(&partial).map_err(|e| error(e.to_string()))?;
+        }
+        let connection = Connection::open(&partial).map_err(|e| error(e.to_string()))?;
+        connection
+            .execute_batch(
+                "CREATE TABLE metadata (name TEXT, value TEXT);
+                 CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+                 CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);
+                 BEGIN;",
+            )
+            .map_err(|e| error(e.to_string()))?;
+        Ok(Self::MBTiles {
+            connection,
+            partial,
+            path: path.to_owned(),
+        })
+    }
+
+    pub fn write(&mut self, id: TileId, data: &[u8]) -> Result<(), String> {
+        match self {
+            Self::Directory(path) => {
+                let dir = path.join(id.z.to_string()).join(id.x.to_string());
+                fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+                let file = dir.join(format!("{}.mvt", id.y));
+                fs::write(&file, data).map_err(|e| format!("{}: {}", file.display(), e))
+            }
+            Self::MBTiles { connection, .. } => {
+                let mut encoder = GzEncoder::new(vec![], Compression::default());
+                encoder.write_all(data).map_err(|e| e.to_string())?;
+                let data = encoder.finish().map_err(|e| e.to_string())?;
+                let row = flip_row(id.z, id.y);
+                connection
+                    .execute(
+                        "INSERT INTO tiles VALUES (?1, ?2, ?3, ?4)",
+                        params![id.z, id.x, row, data],
+                    )
+                    .map(|_| ())
+                    .map_err(|e| format!("tile {}: {}", id, e))
+            }
+        }
+    }
+
+    // Metadata is only stored in archives
+    pub fn finish(self, metadata: &[(&str, String)]) -> Result<(), String> {
+        let Self::MBTiles {
+            connection,
+            partial,
+            path,
+        } = self
+        else {
+            return Ok(());
+        };
+        for (name, value) in metadata {
+            connection
+                .execute("INSERT INTO metadata VALUES (?1, ?2)", params![name, value])
+                .map_err(|e| e.to_string())?;
+        }
+        connection
+            .execute_batch("COMMIT;")
+            .map_err(|e| e.to_string())?;
+        connection.close().map_err(|(_, e)| e.to_string())?;
+        fs::rename(&partial, &path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mbtiles_written_on_finish() {
+        let dir = std::env::temp_dir().join(format!("mapstick-writer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tiles.mbtiles");
+        let id = TileId::new(0, 0, 0);
+
+        // A run that fails before finishing leaves nothing behind that stops
+        // the next one
+        let mut writer = TileWriter::create(&path).unwrap();
+        writer.write(id, &[1, 2, 3]).unwrap();
+        drop(writer);
+        assert!(!path.exists());
+
+        let mut writer = TileWriter::create(&path).unwrap();
+        writer.write(id, &[1, 2, 3]).unwrap();
+        writer.finish(&[("name", "test".to_owned())]).unwrap();
+        assert!(path.exists());
+        assert!(!path.with_extension("mbtiles.partial").exists());
+        assert!(TileWriter::create(&path).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
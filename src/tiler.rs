@@ -0,0 +1,315 @@
+use std::collections::{BTreeMap, HashMap};
+
+use prost::Message;
+use serde_json::json;
+use vello::kurbo::{Point, Rect};
+
+use crate::{
+    clip::{clip_line, clip_ring},
+    encoder::{LayerEncoder, encode_tile},
+    geojson::{GeoFeature, GeoGeometry},
+    geometry::{Coord, MultiPolygon, Polygon, TypedGeometry, signed_area},
+    projection::lon_lat_to_world,
+    properties::PropertyValue,
+    simplify::simplify,
+    tile_id::{TILE_BOUNDS, TileId},
+    tile_writer::TileWriter,
+};
+
+pub struct TilerOptions {
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub layer: String,
+    pub extent: u32,
+    // Fraction of the tile width kept around every tile
+    pub buffer: f64,
+    // Douglas–Peucker tolerance in tile coordinates (1/extent of a tile)
+    pub tolerance: f64,
+}
+
+impl Default for TilerOptions {
+    fn default() -> Self {
+        Self {
+            min_zoom: 0,
+            max_zoom: 14,
+            layer: "overlay".to_owned(),
+            extent: 4096,
+            buffer: 1.0 / 64.0,
+            tolerance: 1.0,
+        }
+    }
+}
+
+// Feature projected to world coordinates (the unit square)
+struct WorldFeature<'a> {
+    source: &'a GeoFeature,
+    geometry: GeoGeometry,
+    bbox: Rect,
+}
+
+// Slices the features into tiles of every zoom level in the range and writes
+// the non-empty ones, returns the number of tiles written
+pub fn write_tiles(
+    features: &[GeoFeature],
+    options: &TilerOptions,
+    mut writer: TileWriter,
+) -> Result<usize, String> {
+    let features: Vec<WorldFeature> = features.iter().filter_map(project).collect();
+    let mut count = 0;
+    for z in options.min_zoom..=options.max_zoom {
+        let tiles = slice(&features, z, options);
+        log::info!("zoom {}: {} tiles", z, tiles.len());
+        for (id, layer) in tiles {
+            let tile = encode_tile(vec![layer]);
+            writer.write(id, &tile.encode_to_vec())?;
+            count += 1;
+        }
+    }
+    writer.finish(&metadata(&features, options))?;
+    Ok(count)
+}
+
+fn slice(
+    features: &[WorldFeature],
+    z: u8,
+    options: &TilerOptions,
+) -> BTreeMap<TileId, LayerEncoder> {
+    let mut tiles = HashMap::new();
+    let world_size = (1_u64 << z) as f64;
+    let tolerance = options.tolerance / (options.extent as f64 * world_size);
+    let buffer = options.buffer / world_size;
+    let clip_rect = TILE_BOUNDS.inflate(options.buffer, options.buffer);
+
+    for feature in features {
+        // Simplified once per zoom level rather than per tile, so that tiles
+        // sharing an edge agree on it
+        let geometry = simplify_geometry(&feature.geometry, tolerance);
+        for id in TileId::covering(feature.bbox.inflate(buffer, buffer), z) {
+            let to_tile = id.transform().inverse();
+            let Some(typed) = tile_geometry(&geometry, |p| to_tile * p, clip_rect, options.extent)
+            else {
+                continue;
+            };
+            tiles
+                .entry(id)
+                .or_insert_with(|| LayerEncoder::new(&options.layer, options.extent))
+                .add_feature(feature.source.id, &typed, &feature.source.properties);
+        }
+    }
+    tiles.into_iter().collect()
+}
+
+fn project(feature: &GeoFeature) -> Option<WorldFeature<'_>> {
+    let map = |points: &Vec<Point>| points.iter().map(|&p| lon_lat_to_world(p)).collect();
+    let geometry = match &feature.geometry {
+        GeoGeometry::Points(points) => GeoGeometry::Points(map(points)),
+        GeoGeometry::Lines(lines) => GeoGeometry::Lines(lines.iter().map(map).collect()),
+        GeoGeometry::Polygons(polygons) => GeoGeometry::Polygons(
+            polygons
+                .iter()
+                .map(|rings| rings.iter().map(map).collect())
+                .collect(),
+        ),
+    };
+    let bbox = points(&geometry)
+        .map(|p| Rect::from_points(p, p))
+        .reduce(|acc, r| acc.union(r))?;
+    Some(WorldFeature {
+        source: feature,
+        geometry,
+        bbox,
+    })
+}
+
+fn points(geometry: &GeoGeometry) -> Box<dyn Iterator<Item = Point> + '_> {
+    match geometry {
+        GeoGeometry::Points(points) => Box::new(points.iter().copied()),
+        GeoGeometry::Lines(lines) => Box::new(lines.iter().flatten().copied()),
+        GeoGeometry::Polygons(polygons) => Box::new(polygons.iter().flatten().flatten().copied()),
+    }
+}
+
+fn simplify_geometry(geometry: &GeoGeometry, tolerance: f64) -> GeoGeometry {
+    match geometry {
+        GeoGeometry::Points(points) => GeoGeometry::Points(points.clone()),
+        GeoGeometry::Lines(lines) => {
+            GeoGeometry::Lines(lines.iter().map(|l| simplify(l, tolerance)).collect())
+        }
+        GeoGeometry::Polygons(polygons) => GeoGeometry::Polygons(
+            polygons
+                .iter()
+                .map(|rings| rings.iter().map(|r| simplify(r, tolerance)).collect())
+                .collect(),
+        ),
+    }
+}
+
+// Clips the geometry to the buffered tile and rounds it to the tile grid,
+// parts that collapse are dropped
+fn tile_geometry(
+    geometry: &GeoGeometry,
+    to_tile: impl Fn(Point) -> Point,
+    clip_rect: Rect,
+    extent: u32,
+) -> Option<TypedGeometry> {
+    let to_tile_all = |points: &[Point]| points.iter().map(|&p| to_tile(p)).collect::<Vec<_>>();
+    match geometry {
+        GeoGeometry::Points(points) => {
+            let mut points: Vec<Coord> = to_tile_all(points)
+                .into_iter()
+                .filter(|&p| clip_rect.contains(p))
+                .map(|p| to_coord(p, extent))
+                .collect();
+            match points.len() {
+                0 => None,
+                1 => Some(TypedGeometry::Point(points.remove(0))),
+                _ => Some(TypedGeometry::MultiPoint(points)),
+            }
+        }
+        GeoGeometry::Lines(lines) => {
+            let mut lines: Vec<Vec<Coord>> = lines
+                .iter()
+                .flat_map(|line| clip_line(&to_tile_all(line), clip_rect))
+                .map(|line| to_coords(&line, extent))
+                .filter(|line| line.len() >= 2)
+                .collect();
+            match lines.len() {
+                0 => None,
+                1 => Some(TypedGeometry::LineString(lines.remove(0))),
+                _ => Some(TypedGeometry::MultiLineString(lines)),
+            }
+        }
+        GeoGeometry::Polygons(polygons) => {
+            let ring = |ring: &Vec<Point>| {
+                let mut ring = to_coords(&clip_ring(&to_tile_all(ring), clip_rect), extent);
+                if ring.len() > 1 && ring.first() == ring.last() {
+                    ring.pop();
+                }
+                (signed_area(&ring) != 0).then_some(ring)
+            };
+            let mut polygons: Vec<Polygon> = polygons
+                .iter()
+                .filter_map(|rings| {
+                    let (exterior, interiors) = rings.split_first()?;
+                    Some(Polygon {
+                        exterior: ring(exterior)?,
+                        interiors: interiors.iter().filter_map(ring).collect(),
+                    })
+                })
+                .collect();
+            match polygons.len() {
+                0 => None,
+                1 => Some(TypedGeometry::Polygon(polygons.remove(0))),
+                _ => Some(TypedGeometry::MultiPolygon(MultiPolygon { polygons })),
+            }
+        }
+    }
+}
+
+fn to_coord(p: Point, extent: u32) -> Coord {
+    Coord {
+        x: (p.x * extent as f64).round() as i32,
+        y: (p.y * extent as f64).round() as i32,
+    }
+}
+
+// Consecutive points that round to the same coordinates are merged
+fn to_coords(points: &[Point], extent: u32) -> Vec<Coord> {
+    let mut res: Vec<Coord> = points.iter().map(|&p| to_coord(p, extent)).collect();
+    res.dedup();
+    res
+}
+
+// MBTiles metadata, see the "vector_layers" section of the MBTiles specification
+fn metadata(features: &[WorldFeature], options: &TilerOptions) -> Vec<(&'static str, String)> {
+    let mut fields = BTreeMap::new();
+    for feature in features {
+        for (key, value) in feature.source.properties.iter() {
+            let kind = match value {
+                PropertyValue::String(_) => "String",
+                PropertyValue::Bool(_) => "Boolean",
+                _ => "Number",
+            };
+            fields.insert(key.clone(), kind);
+        }
+    }
+    let vector_layers = json!({
+        "vector_layers": [{
+            "id": options.layer,
+            "fields": fields,
+            "minzoom": options.min_zoom,
+            "maxzoom": options.max_zoom,
+        }]
+    });
+
+    let mut res = vec![
+        ("name", options.layer.clone()),
+        ("format", "pbf".to_owned()),
+        ("type", "overlay".to_owned()),
+        ("minzoom", options.min_zoom.to_string()),
+        ("maxzoom", options.max_zoom.to_string()),
+        ("json", vector_layers.to_string()),
+    ];
+    let bounds = features
+        .iter()
+        .map(|f| f.source)
+        .flat_map(|f| points(&f.geometry));
+    if let Some(bounds) = bounds
+        .map(|p| Rect::from_points(p, p))
+        .reduce(|a, b| a.union(b))
+    {
+        res.push((
+            "bounds",
+            format!("{},{},{},{}", bounds.x0, bounds.y0, bounds.x1, bounds.y1),
+        ));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{geojson::read_features, tile_loader::decode_layers, tile_source::TileSource};
+
+    #[test]
+    fn test_write_tiles() {
+        // A square around (0, 0) touching all four tiles of zoom 1, and a
+        // line in the north-western tile
+        let text = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "id": 1, "properties": {"name": "square"}, "geometry": {
+                "type": "Polygon",
+                "coordinates": [[[-10, -10], [10, -10], [10, 10], [-10, 10], [-10, -10]]]
+            }},
+            {"type": "Feature", "id": 2, "properties": {"name": "line"}, "geometry": {
+                "type": "LineString", "coordinates": [[-100, 40], [-50, 45], [-20, 50]]
+            }}
+        ]}"#;
+        let features = read_features(text).unwrap();
+        let dir = std::env::temp_dir().join(format!("mapstick-tiler-{}", std::process::id()));
+        let options = TilerOptions {
+            min_zoom: 0,
+            max_zoom: 1,
+            ..Default::default()
+        };
+        let count = write_tiles(&features, &options, TileWriter::create(&dir).unwrap()).unwrap();
+        assert_eq!(count, 5);
+
+        let source = TileSource::open(dir.clone(), TileId::new(0, 0, 0)).unwrap();
+        assert_eq!(source.zoom_range(), (0, 1));
+        let data = source.read(TileId::new(1, 0, 0)).unwrap().unwrap();
+        let layers = decode_layers(&data, None).unwrap();
+        assert_eq!(layers[0].name(), "overlay");
+        assert_eq!(layers[0].features.len(), 2);
+        let (geometry, ring_issues) = layers[0].features[0].geometry().unwrap();
+        let TypedGeometry::Polygon(square) = geometry else {
+            panic!("not a polygon");
+        };
+        // Clipped to the buffered tile: the square's corner is at the tile's
+        // south-east corner (4096, 4096), cut at 4096 + 64
+        let max_x = square.exterior.iter().map(|c| c.x).max().unwrap();
+        assert_eq!(max_x, 4096 + 64);
+        assert!(ring_issues.is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
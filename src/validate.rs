@@ -0,0 +1,534 @@
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    Tile,
+    geometry::{Command, Coord, DecodedCommand, DecodedParameter, signed_area},
+    layer_wrapper::SUPPORTED_VERSIONS,
+    properties::PropertyValue,
+    tile::{Feature, GeomType, Layer, Value},
+};
+
+// Requirements of the specification: MUST ones are errors, SHOULD ones warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// A way a tile departs from version 2.1 of the specification, section numbers
+// are given where the requirement is stated
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    // 4.1
+    DuplicateLayerName,
+    EmptyLayerName,
+    UnsupportedVersion(u32),
+    ZeroExtent,
+    // 4.2
+    DuplicateFeatureId(u64),
+    // 4.3.4.1 allows UNKNOWN, decoders may ignore such features
+    UnknownGeometryType,
+    // 4.1 (keys and values tables)
+    DuplicateKey(String),
+    DuplicateValue(usize),
+    UnusedKey(String),
+    UnusedValue(usize),
+    InvalidValue(usize),
+    // 4.4
+    OddTagCount(usize),
+    KeyIndexOutOfRange(u32),
+    ValueIndexOutOfRange(u32),
+    // 4.3
+    EmptyGeometry,
+    InvalidCommand(u32),
+    MissingParameters,
+    ParameterOutOfRange(u32),
+    FirstCommandNotMoveTo,
+    // 4.3.4.2: a single MoveTo with a positive count
+    PointCommand(Command, u32),
+    // 4.3.4.3 and 4.3.4.4: every part starts with a MoveTo of count 1
+    MoveToCount(u32),
+    // LineTo count 0
+    LineToCount,
+    // 4.3.3.2
+    ZeroLengthLineTo,
+    // A line with a single point
+    LineTooShort,
+    ClosePathInLine,
+    // 4.3.3.3
+    ClosePathCount(u32),
+    // 4.3.4.4
+    RingTooShort(usize),
+    MissingClosePath,
+    ZeroAreaRing,
+    FirstRingInterior,
+}
+
+impl Violation {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Violation::DuplicateFeatureId(_)
+            | Violation::DuplicateKey(_)
+            | Violation::DuplicateValue(_)
+            | Violation::UnusedKey(_)
+            | Violation::UnusedValue(_)
+            | Violation::UnknownGeometryType => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::DuplicateLayerName => write!(f, "another layer has the same name"),
+            Violation::EmptyLayerName => write!(f, "empty layer name"),
+            Violation::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            Violation::ZeroExtent => write!(f, "zero extent"),
+            Violation::DuplicateFeatureId(id) => write!(f, "id {} is not unique", id),
+            Violation::UnknownGeometryType => {
+                write!(f, "unknown geometry type, decoders may ignore the feature")
+            }
+            Violation::DuplicateKey(key) => write!(f, "key {} is listed twice", key),
+            Violation::DuplicateValue(i) => write!(f, "value {} is listed twice", i),
+            Violation::UnusedKey(key) => write!(f, "key {} is never used", key),
+            Violation::UnusedValue(i) => write!(f, "value {} is never used", i),
+            Violation::InvalidValue(i) => write!(f, "value {} does not have exactly one field", i),
+            Violation::OddTagCount(n) => write!(f, "odd number of tags ({})", n),
+            Violation::KeyIndexOutOfRange(i) => write!(f, "key index {} out of range", i),
+            Violation::ValueIndexOutOfRange(i) => write!(f, "value index {} out of range", i),
+            Violation::EmptyGeometry => write!(f, "empty geometry"),
+            Violation::InvalidCommand(c) => write!(f, "invalid command integer {}", c),
+            Violation::MissingParameters => write!(f, "command stream ends within parameters"),
+            Violation::ParameterOutOfRange(p) => write!(f, "parameter {} out of range", p),
+            Violation::FirstCommandNotMoveTo => write!(f, "first command is not MoveTo"),
+            Violation::PointCommand(c, n) => {
+                write!(f, "point geometry has a {:?} command with count {}", c, n)
+            }
+            Violation::MoveToCount(n) => write!(f, "MoveTo count {} instead of 1", n),
+            Violation::LineToCount => write!(f, "LineTo count 0"),
+            Violation::ZeroLengthLineTo => write!(f, "LineTo of length 0"),
+            Violation::LineTooShort => write!(f, "line with a single point"),
+            Violation::ClosePathInLine => write!(f, "ClosePath in a line"),
+            Violation::ClosePathCount(n) => write!(f, "ClosePath count {} instead of 1", n),
+            Violation::RingTooShort(n) => write!(f, "ring with {} points", n),
+            Violation::MissingClosePath => write!(f, "ring without ClosePath"),
+            Violation::ZeroAreaRing => write!(f, "ring with zero area"),
+            Violation::FirstRingInterior => write!(f, "first ring is an interior ring"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub layer: String,
+    // Position of the feature in its layer
+    pub feature: Option<usize>,
+    pub violation: Violation,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.violation.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: layer {}", severity, self.layer)?;
+        if let Some(feature) = self.feature {
+            write!(f, ", feature {}", feature)?;
+        }
+        write!(f, ": {}", self.violation)
+    }
+}
+
+pub fn validate_tile(tile: &Tile) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    let mut names = HashSet::new();
+    for layer in tile.layers.iter() {
+        if !names.insert(layer.name.as_str()) {
+            res.push(Diagnostic {
+                layer: layer.name.clone(),
+                feature: None,
+                violation: Violation::DuplicateLayerName,
+            });
+        }
+        res.extend(validate_layer(layer));
+    }
+    res
+}
+
+pub fn validate_layer(layer: &Layer) -> Vec<Diagnostic> {
+    let mut violations: Vec<(Option<usize>, Violation)> = vec![];
+    let mut report = |feature, violation| violations.push((feature, violation));
+
+    if layer.name.is_empty() {
+        report(None, Violation::EmptyLayerName);
+    }
+    if !SUPPORTED_VERSIONS.contains(&layer.version) {
+        report(None, Violation::UnsupportedVersion(layer.version));
+    }
+    if layer.extent() == 0 {
+        report(None, Violation::ZeroExtent);
+    }
+
+    let mut keys = HashSet::new();
+    for key in layer.keys.iter() {
+        if !keys.insert(key) {
+            report(None, Violation::DuplicateKey(key.clone()));
+        }
+    }
+    let mut values = HashSet::new();
+    for (i, value) in layer.values.iter().enumerate() {
+        match PropertyValue::try_from(value) {
+            Ok(property) if field_count(value) == 1 => {
+                if !values.insert(property) {
+                    report(None, Violation::DuplicateValue(i));
+                }
+            }
+            _ => report(None, Violation::InvalidValue(i)),
+        }
+    }
+
+    let mut used_keys = vec![false; layer.keys.len()];
+    let mut used_values = vec![false; layer.values.len()];
+    let mut ids = HashSet::new();
+    for (i, feature) in layer.features.iter().enumerate() {
+        if let Some(id) = feature.id {
+            if !ids.insert(id) {
+                report(Some(i), Violation::DuplicateFeatureId(id));
+            }
+        }
+
+        if feature.tags.len() % 2 != 0 {
+            report(Some(i), Violation::OddTagCount(feature.tags.len()));
+        }
+        for pair in feature.tags.chunks_exact(2) {
+            match used_keys.get_mut(pair[0] as usize) {
+                Some(used) => *used = true,
+                None => report(Some(i), Violation::KeyIndexOutOfRange(pair[0])),
+            }
+            match used_values.get_mut(pair[1] as usize) {
+                Some(used) => *used = true,
+                None => report(Some(i), Violation::ValueIndexOutOfRange(pair[1])),
+            }
+        }
+
+        for violation in validate_geometry(feature, layer.version) {
+            report(Some(i), violation);
+        }
+    }
+    for (key, used) in layer.keys.iter().zip(used_keys) {
+        if !used {
+            report(None, Violation::UnusedKey(key.clone()));
+        }
+    }
+    for (i, used) in used_values.into_iter().enumerate() {
+        if !used {
+            report(None, Violation::UnusedValue(i));
+        }
+    }
+
+    violations
+        .into_iter()
+        .map(|(feature, violation)| Diagnostic {
+            layer: layer.name.clone(),
+            feature,
+            violation,
+        })
+        .collect()
+}
+
+fn field_count(value: &Value) -> usize {
+    [
+        value.string_value.is_some(),
+        value.float_value.is_some(),
+        value.double_value.is_some(),
+        value.int_value.is_some(),
+        value.uint_value.is_some(),
+        value.sint_value.is_some(),
+        value.bool_value.is_some(),
+    ]
+    .into_iter()
+    .filter(|&set| set)
+    .count()
+}
+
+// Checks the command stream against section 4.3, stopping at the first
+// violation that makes the rest of the stream unreadable
+fn validate_geometry(feature: &Feature, version: u32) -> Vec<Violation> {
+    let mut res = vec![];
+    let geom_type = feature.r#type();
+    if geom_type == GeomType::Unknown {
+        res.push(Violation::UnknownGeometryType);
+        return res;
+    }
+    if feature.geometry.is_empty() {
+        res.push(Violation::EmptyGeometry);
+        return res;
+    }
+
+    let mut stream = feature.geometry.iter();
+    let mut cursor = Coord::default();
+    let mut commands = vec![];
+    while let Some(&command_int) = stream.next() {
+        let Ok(command) = DecodedCommand::try_from(command_int) else {
+            res.push(Violation::InvalidCommand(command_int));
+            return res;
+        };
+        let mut points = vec![];
+        if command.command() != Command::ClosePath {
+            for _ in 0..command.count() {
+                let (Some(&x), Some(&y)) = (stream.next(), stream.next()) else {
+                    res.push(Violation::MissingParameters);
+                    return res;
+                };
+                let (dx, dy) = (DecodedParameter::from(x), DecodedParameter::from(y));
+                for (param, encoded) in [(&dx, x), (&dy, y)] {
+                    if DecodedParameter::try_from(param.raw_value).is_err() {
+                        res.push(Violation::ParameterOutOfRange(encoded));
+                    }
+                }
+                if command.command() == Command::LineTo && dx.raw_value == 0 && dy.raw_value == 0 {
+                    res.push(Violation::ZeroLengthLineTo);
+                }
+                cursor = Coord {
+                    x: cursor.x.wrapping_add(dx.raw_value),
+                    y: cursor.y.wrapping_add(dy.raw_value),
+                };
+                points.push(cursor);
+            }
+        }
+        commands.push((command, points));
+    }
+
+    if commands[0].0.command() != Command::MoveTo {
+        res.push(Violation::FirstCommandNotMoveTo);
+        return res;
+    }
+    match geom_type {
+        GeomType::Point => {
+            for (i, (command, _)) in commands.iter().enumerate() {
+                if i > 0 || command.count() == 0 {
+                    res.push(Violation::PointCommand(command.command(), command.count()));
+                }
+            }
+        }
+        GeomType::Linestring => {
+            for part in split_parts(&commands, &mut res) {
+                if part.iter().any(|(c, _)| c.command() == Command::ClosePath) {
+                    res.push(Violation::ClosePathInLine);
+                }
+                if part.iter().map(|(_, points)| points.len()).sum::<usize>() < 2 {
+                    res.push(Violation::LineTooShort);
+                }
+            }
+        }
+        GeomType::Polygon => {
+            for (i, part) in split_parts(&commands, &mut res).into_iter().enumerate() {
+                let ring: Vec<Coord> = part.iter().flat_map(|(_, p)| p.iter().copied()).collect();
+                if ring.len() < 3 {
+                    res.push(Violation::RingTooShort(ring.len()));
+                }
+                match part.last() {
+                    Some((c, _)) if c.command() == Command::ClosePath => {
+                        if c.count() != 1 {
+                            res.push(Violation::ClosePathCount(c.count()));
+                        }
+                    }
+                    _ => res.push(Violation::MissingClosePath),
+                }
+                let area = signed_area(&ring);
+                if area == 0 {
+                    res.push(Violation::ZeroAreaRing);
+                } else if i == 0 && area < 0 && version >= 2 {
+                    res.push(Violation::FirstRingInterior);
+                }
+            }
+        }
+        GeomType::Unknown => unreachable!(),
+    }
+    res
+}
+
+type Commands = [(DecodedCommand, Vec<Coord>)];
+
+// Splits lines and polygons at every MoveTo, checking command counts
+fn split_parts<'a>(commands: &'a Commands, res: &mut Vec<Violation>) -> Vec<&'a Commands> {
+    let mut parts = vec![];
+    let mut start = 0;
+    for (i, (command, _)) in commands.iter().enumerate() {
+        match command.command() {
+            Command::MoveTo => {
+                if command.count() != 1 {
+                    res.push(Violation::MoveToCount(command.count()));
+                }
+                if i > start {
+                    parts.push(&commands[start..i]);
+                }
+                start = i;
+            }
+            Command::LineTo if command.count() == 0 => res.push(Violation::LineToCount),
+            _ => (),
+        }
+    }
+    parts.push(&commands[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::encoder::{LayerEncoder, encode_tile};
+    use crate::geometry::{Polygon, TypedGeometry};
+
+    fn feature(geom_type: GeomType, geometry: Vec<u32>) -> Feature {
+        Feature {
+            id: None,
+            tags: vec![],
+            r#type: Some(geom_type as i32),
+            geometry,
+        }
+    }
+
+    fn violations(geom_type: GeomType, geometry: Vec<u32>) -> Vec<Violation> {
+        validate_geometry(&feature(geom_type, geometry), 2)
+    }
+
+    #[test]
+    fn test_valid_geometries() {
+        // Examples from section 4.3.5 of the specification
+        assert!(violations(GeomType::Point, vec![9, 50, 34]).is_empty());
+        assert!(violations(GeomType::Point, vec![17, 10, 14, 3, 9]).is_empty());
+        assert!(violations(GeomType::Linestring, vec![9, 4, 4, 18, 0, 16, 16, 0]).is_empty());
+        let lines = vec![9, 4, 4, 18, 0, 16, 16, 0, 9, 17, 17, 10, 4, 8];
+        assert!(violations(GeomType::Linestring, lines).is_empty());
+        let polygon = vec![9, 6, 12, 18, 10, 12, 24, 44, 15];
+        assert!(violations(GeomType::Polygon, polygon).is_empty());
+        let polygons = vec![
+            9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15, 9, 22, 2, 26, 18, 0, 0, 18, 17, 0, 15, 9, 4, 13,
+            26, 0, 8, 8, 0, 0, 7, 15,
+        ];
+        assert!(violations(GeomType::Polygon, polygons).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_geometries() {
+        assert_eq!(
+            violations(GeomType::Point, vec![9, 50, 34, 10, 2, 2]),
+            vec![Violation::PointCommand(Command::LineTo, 1)]
+        );
+        assert_eq!(
+            violations(GeomType::Linestring, vec![17, 4, 4, 6, 6, 2]),
+            vec![Violation::MoveToCount(2), Violation::LineToCount]
+        );
+        assert_eq!(
+            violations(GeomType::Linestring, vec![9, 4, 4, 10, 0, 0]),
+            vec![Violation::ZeroLengthLineTo]
+        );
+        assert_eq!(
+            violations(GeomType::Linestring, vec![10, 4, 4]),
+            vec![Violation::FirstCommandNotMoveTo]
+        );
+        assert_eq!(
+            violations(GeomType::Polygon, vec![9, 6, 12, 10, 10, 12]),
+            vec![
+                Violation::RingTooShort(2),
+                Violation::MissingClosePath,
+                Violation::ZeroAreaRing
+            ]
+        );
+        // Counter-clockwise, allowed in version 1 only
+        assert_eq!(
+            violations(GeomType::Polygon, vec![9, 6, 12, 18, 24, 44, 10, 12, 15]),
+            vec![Violation::FirstRingInterior]
+        );
+        assert!(
+            validate_geometry(
+                &feature(GeomType::Polygon, vec![9, 6, 12, 18, 24, 44, 10, 12, 15]),
+                1
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            violations(GeomType::Point, vec![9, 0xffffffff, 0]),
+            vec![Violation::ParameterOutOfRange(0xffffffff)]
+        );
+        assert_eq!(
+            violations(GeomType::Point, vec![9, 2]),
+            vec![Violation::MissingParameters]
+        );
+        assert_eq!(
+            violations(GeomType::Point, vec![12]),
+            vec![Violation::InvalidCommand(12)]
+        );
+    }
+
+    #[test]
+    fn test_validate_tile() {
+        let mut encoder = LayerEncoder::new("overlay", 4096);
+        let square = Polygon {
+            exterior: [(0, 0), (10, 0), (10, 10), (0, 10)]
+                .iter()
+                .map(|&(x, y)| Coord { x, y })
+                .collect(),
+            interiors: vec![],
+        };
+        let properties = [("name".to_owned(), PropertyValue::String("a".to_owned()))];
+        encoder.add_feature(Some(1), &TypedGeometry::Polygon(square), &properties);
+        let mut tile = encode_tile(vec![encoder]);
+        assert!(validate_tile(&tile).is_empty());
+
+        let mut layer = tile.layers[0].clone();
+        layer.keys.push("name".to_owned());
+        layer.values.push(Value::default());
+        layer.features.push(Feature {
+            id: Some(1),
+            tags: vec![0, 5, 1],
+            ..layer.features[0].clone()
+        });
+        tile.layers.push(layer);
+
+        // Survives encoding, the validator works on decoded tiles
+        let tile = Tile::decode(tile.encode_to_vec().as_slice()).unwrap();
+        let diagnostics = validate_tile(&tile);
+        let violations: Vec<(Option<usize>, Violation)> = diagnostics
+            .iter()
+            .map(|d| (d.feature, d.violation.clone()))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![
+                (None, Violation::DuplicateLayerName),
+                (None, Violation::DuplicateKey("name".to_owned())),
+                (None, Violation::InvalidValue(1)),
+                (Some(1), Violation::DuplicateFeatureId(1)),
+                (Some(1), Violation::OddTagCount(3)),
+                (Some(1), Violation::ValueIndexOutOfRange(5)),
+                (None, Violation::UnusedKey("name".to_owned())),
+                (None, Violation::UnusedValue(1)),
+            ]
+        );
+        assert_eq!(
+            diagnostics[4].to_string(),
+            "error: layer overlay, feature 1: odd number of tags (3)"
+        );
+    }
+
+    #[test]
+    fn test_unknown_geometry_type() {
+        let mut encoder = LayerEncoder::new("overlay", 4096);
+        let point = TypedGeometry::Point(Coord { x: 1, y: 2 });
+        encoder.add_feature(None, &point, &[]);
+        let mut tile = encode_tile(vec![encoder]);
+        let unknown = feature(GeomType::Unknown, vec![]);
+        tile.layers[0].features.push(unknown);
+
+        // Allowed by the specification, so it doesn't make the tile invalid
+        let diagnostics = validate_tile(&tile);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].violation, Violation::UnknownGeometryType);
+        assert_eq!(diagnostics[0].violation.severity(), Severity::Warning);
+    }
+}